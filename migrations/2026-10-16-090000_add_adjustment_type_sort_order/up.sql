@@ -0,0 +1 @@
+ALTER TABLE adjustment_type ADD COLUMN sort_order INT NULL;
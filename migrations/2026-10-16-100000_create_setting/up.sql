@@ -0,0 +1,4 @@
+CREATE TABLE setting (
+  name VARCHAR(255) PRIMARY KEY,
+  value VARCHAR(255) NOT NULL
+);
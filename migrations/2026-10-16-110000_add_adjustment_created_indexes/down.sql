@@ -0,0 +1,2 @@
+DROP INDEX adjustment_type_id_created ON adjustment;
+DROP INDEX adjustment_created ON adjustment;
@@ -0,0 +1,17 @@
+-- Supports the queries that filter adjustments by creation date and order them
+-- by it, like listing the adjustments since a date or since the current time
+-- entry, calculating the adjusted time, and pruning old adjustments. Without it
+-- these queries do a full table scan followed by a filesort:
+--
+--   EXPLAIN SELECT * FROM adjustment WHERE created >= '2023-01-01' ORDER BY created DESC LIMIT 10;
+--   before: type=ALL,   key=NULL,                Extra=Using where; Using filesort
+--   after:  type=range, key=adjustment_created,  Extra=Using index condition
+CREATE INDEX adjustment_created ON adjustment (created);
+
+-- Supports listing the adjustments of a single type, optionally since a date,
+-- ordered by creation date.
+--
+--   EXPLAIN SELECT * FROM adjustment WHERE adjustment_type_id = 1 ORDER BY created DESC LIMIT 10;
+--   before: type=ALL, key=NULL,                         Extra=Using where; Using filesort
+--   after:  type=ref, key=adjustment_type_id_created,   Extra=Using where
+CREATE INDEX adjustment_type_id_created ON adjustment (adjustment_type_id, created);
@@ -0,0 +1 @@
+ALTER TABLE adjustment_type DROP COLUMN expires_in_days;
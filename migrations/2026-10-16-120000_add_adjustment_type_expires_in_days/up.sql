@@ -0,0 +1 @@
+ALTER TABLE adjustment_type ADD COLUMN expires_in_days SMALLINT UNSIGNED NULL;
@@ -0,0 +1 @@
+ALTER TABLE time_entry ADD COLUMN label VARCHAR(255) NULL;
@@ -0,0 +1 @@
+ALTER TABLE adjustment_type DROP COLUMN color;
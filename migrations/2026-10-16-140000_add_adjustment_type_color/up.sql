@@ -0,0 +1 @@
+ALTER TABLE adjustment_type ADD COLUMN color VARCHAR(7) NULL;
@@ -0,0 +1 @@
+ALTER TABLE adjustment_type MODIFY adjustment TINYINT SIGNED NOT NULL;
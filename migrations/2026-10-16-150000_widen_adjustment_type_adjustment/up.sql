@@ -0,0 +1 @@
+ALTER TABLE adjustment_type MODIFY adjustment INT SIGNED NOT NULL;
@@ -0,0 +1,2 @@
+ALTER TABLE adjustment DROP COLUMN source;
+ALTER TABLE time_entry DROP COLUMN source;
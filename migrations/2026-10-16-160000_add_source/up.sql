@@ -0,0 +1,2 @@
+ALTER TABLE time_entry ADD COLUMN source VARCHAR(10) NOT NULL DEFAULT 'cli';
+ALTER TABLE adjustment ADD COLUMN source VARCHAR(10) NOT NULL DEFAULT 'cli';
@@ -0,0 +1,5 @@
+DROP TRIGGER time_entry_deleted;
+DROP TRIGGER time_entry_created;
+DROP TRIGGER adjustment_deleted;
+DROP TRIGGER adjustment_created;
+DROP TABLE change_log;
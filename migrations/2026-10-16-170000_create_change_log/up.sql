@@ -0,0 +1,26 @@
+-- Every creation and deletion of an adjustment or time entry gets a new, increasing ID, which is
+-- used as the cursor of the changes feed. The log is kept up to date by triggers, so records that
+-- are inserted or deleted in bulk are logged too.
+CREATE TABLE change_log (
+  id SERIAL PRIMARY KEY,
+  record_type VARCHAR(20) NOT NULL,
+  record_id BIGINT UNSIGNED NOT NULL,
+  deleted BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+INSERT INTO change_log (record_type, record_id)
+SELECT record_type, record_id FROM (
+  SELECT 'time_entry' AS record_type, id AS record_id, created, 0 AS position FROM time_entry
+  UNION ALL
+  SELECT 'adjustment', id, created, 1 FROM adjustment
+) AS existing
+ORDER BY created, position, record_id;
+
+CREATE TRIGGER adjustment_created AFTER INSERT ON adjustment FOR EACH ROW
+  INSERT INTO change_log (record_type, record_id) VALUES ('adjustment', NEW.id);
+CREATE TRIGGER adjustment_deleted AFTER DELETE ON adjustment FOR EACH ROW
+  INSERT INTO change_log (record_type, record_id, deleted) VALUES ('adjustment', OLD.id, TRUE);
+CREATE TRIGGER time_entry_created AFTER INSERT ON time_entry FOR EACH ROW
+  INSERT INTO change_log (record_type, record_id) VALUES ('time_entry', NEW.id);
+CREATE TRIGGER time_entry_deleted AFTER DELETE ON time_entry FOR EACH ROW
+  INSERT INTO change_log (record_type, record_id, deleted) VALUES ('time_entry', OLD.id, TRUE);
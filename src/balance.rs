@@ -0,0 +1,216 @@
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use tabled::Tabled;
+
+/// Applies an adjustment to the adjusted time, as it stands at the moment `now`.
+///
+/// Rewards lapse if they are not used within the expiry period of their type, so a positive
+/// adjustment that was created more than `expires_in_days` days before `now` is ignored. Penalties
+/// never expire. The adjusted time can't go below the given floor, which is 0 unless a small
+/// negative buffer is allowed.
+#[must_use]
+pub fn apply_adjustment(
+    adjusted_time: i32,
+    adjustment: i32,
+    created: NaiveDateTime,
+    expires_in_days: Option<u16>,
+    now: NaiveDateTime,
+    floor: i32,
+) -> i32 {
+    let expired =
+        expires_in_days.is_some_and(|days| created < now - chrono::Duration::days(i64::from(days)));
+    if adjustment > 0 && expired {
+        return adjusted_time;
+    }
+    (adjusted_time + adjustment).max(floor)
+}
+
+/// Tops up the adjusted time with an allowance.
+///
+/// The result doesn't go over the cap. If the adjusted time is already above the cap it is left as
+/// it is, the allowance never takes time away.
+#[must_use]
+pub fn top_up(adjusted_time: i32, allowance: u16, cap: Option<u16>) -> i32 {
+    let topped_up = adjusted_time + i32::from(allowance);
+    match cap {
+        Some(cap) => topped_up.min(i32::from(cap)).max(adjusted_time),
+        None => topped_up,
+    }
+}
+
+/// An event in a simulation script.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SimulationEvent {
+    TimeEntry {
+        created: NaiveDateTime,
+        time: u16,
+    },
+    Adjustment {
+        created: NaiveDateTime,
+        adjustment: i32,
+        #[serde(default)]
+        expires_in_days: Option<u16>,
+    },
+}
+
+impl SimulationEvent {
+    #[must_use]
+    pub fn created(&self) -> NaiveDateTime {
+        match self {
+            SimulationEvent::TimeEntry { created, .. }
+            | SimulationEvent::Adjustment { created, .. } => *created,
+        }
+    }
+
+    // Time entries are applied before adjustments that were created at the same moment, since
+    // those count towards the new time entry.
+    fn order(&self) -> u8 {
+        match self {
+            SimulationEvent::TimeEntry { .. } => 0,
+            SimulationEvent::Adjustment { .. } => 1,
+        }
+    }
+}
+
+/// The adjusted time right after an event of a simulation.
+#[derive(Debug, PartialEq, Tabled)]
+pub struct SimulationStep {
+    pub created: NaiveDateTime,
+    pub event: String,
+    #[tabled(display_with = "crate::models::format_signed_time_human")]
+    pub time: i32,
+}
+
+/// Runs the given events through the balance logic, in chronological order, and returns the
+/// adjusted time after each event. The time can't go below the given floor. Nothing is read from or
+/// written to the database.
+#[must_use]
+pub fn simulate(mut events: Vec<SimulationEvent>, floor: i32) -> Vec<SimulationStep> {
+    events.sort_by_key(|event| (event.created(), event.order()));
+
+    (0..events.len())
+        .map(|i| {
+            let event = &events[i];
+            let now = event.created();
+            // Like `db::get_adjusted_time()`, start from the most recent time entry and apply the
+            // adjustments that were made since.
+            let start = events[..=i]
+                .iter()
+                .rposition(|e| matches!(e, SimulationEvent::TimeEntry { .. }));
+            let mut adjusted_time = match start.map(|start| &events[start]) {
+                Some(SimulationEvent::TimeEntry { time, .. }) => i32::from(*time),
+                _ => 0,
+            };
+            for e in &events[start.map_or(0, |start| start + 1)..=i] {
+                if let SimulationEvent::Adjustment {
+                    created,
+                    adjustment,
+                    expires_in_days,
+                } = e
+                {
+                    adjusted_time = apply_adjustment(
+                        adjusted_time,
+                        *adjustment,
+                        *created,
+                        *expires_in_days,
+                        now,
+                        floor,
+                    );
+                }
+            }
+
+            SimulationStep {
+                created: now,
+                event: match event {
+                    SimulationEvent::TimeEntry { time, .. } => {
+                        format!("Time entry of {time} minutes")
+                    }
+                    SimulationEvent::Adjustment { adjustment, .. } => {
+                        format!("Adjustment of {adjustment:+} minutes")
+                    }
+                },
+                time: adjusted_time,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(day: u32) -> NaiveDateTime {
+        chrono::NaiveDate::from_ymd_opt(2023, 1, day)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn apply_adjustment_does_not_go_below_zero() {
+        assert_eq!(apply_adjustment(10, -5, day(1), None, day(1), 0), 5);
+        assert_eq!(apply_adjustment(10, -20, day(1), None, day(1), 0), 0);
+    }
+
+    #[test]
+    fn apply_adjustment_does_not_go_below_floor() {
+        assert_eq!(apply_adjustment(10, -20, day(1), None, day(1), -15), -10);
+        assert_eq!(apply_adjustment(10, -25, day(1), None, day(1), -15), -15);
+        assert_eq!(apply_adjustment(10, -40, day(1), None, day(1), -15), -15);
+        assert_eq!(apply_adjustment(-15, 20, day(1), None, day(1), -15), 5);
+    }
+
+    #[test]
+    fn apply_adjustment_of_several_hours() {
+        assert_eq!(apply_adjustment(60, 180, day(1), None, day(1), 0), 240);
+        assert_eq!(apply_adjustment(240, -300, day(1), None, day(1), 0), 0);
+    }
+
+    #[test]
+    fn apply_adjustment_ignores_expired_rewards() {
+        assert_eq!(apply_adjustment(10, 5, day(1), Some(7), day(8), 0), 15);
+        assert_eq!(apply_adjustment(10, 5, day(1), Some(7), day(9), 0), 10);
+        // Penalties never expire.
+        assert_eq!(apply_adjustment(10, -5, day(1), Some(7), day(9), 0), 5);
+    }
+
+    #[test]
+    fn top_up_respects_the_cap() {
+        assert_eq!(top_up(30, 60, None), 90);
+        assert_eq!(top_up(30, 60, Some(120)), 90);
+        assert_eq!(top_up(90, 60, Some(120)), 120);
+        // The time is not reduced when it is already above the cap.
+        assert_eq!(top_up(150, 60, Some(120)), 150);
+        assert_eq!(top_up(-20, 15, Some(120)), -5);
+    }
+
+    #[test]
+    fn simulate_returns_timeline() {
+        let events: Vec<SimulationEvent> = serde_json::from_str(
+            r#"[
+                {"type": "adjustment", "created": "2023-01-02T12:00:00", "adjustment": 10, "expires_in_days": 3},
+                {"type": "time_entry", "created": "2023-01-01T12:00:00", "time": 60},
+                {"type": "adjustment", "created": "2023-01-03T12:00:00", "adjustment": -100},
+                {"type": "adjustment", "created": "2023-01-10T12:00:00", "adjustment": 5},
+                {"type": "time_entry", "created": "2023-01-11T12:00:00", "time": 30}
+            ]"#,
+        )
+        .unwrap();
+        let times: Vec<(NaiveDateTime, i32)> = simulate(events, 0)
+            .into_iter()
+            .map(|step| (step.created, step.time))
+            .collect();
+        assert_eq!(
+            times,
+            vec![
+                (day(1), 60),
+                (day(2), 70),
+                (day(3), 0),
+                // The reward of day 2 has expired by now.
+                (day(10), 5),
+                (day(11), 30),
+            ]
+        );
+    }
+}
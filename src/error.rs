@@ -0,0 +1,131 @@
+use axum::body::Body;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+/// An error that is shared between the command line and the web API.
+///
+/// On the command line the error is printed using its `Display` implementation, in the web API it
+/// is returned as a JSON object with a matching status code.
+#[derive(Debug, PartialEq)]
+pub enum AppError {
+    /// The requested resource does not exist.
+    NotFound(String),
+    /// The action conflicts with the current data, e.g. deleting an adjustment type that is still
+    /// in use.
+    Conflict(String),
+    /// The input is invalid.
+    Validation(String),
+    /// A database query failed.
+    Database(String),
+    /// The application is not configured correctly.
+    Config(String),
+}
+
+impl AppError {
+    /// Returns the HTTP status code that is used for this error in the web API.
+    #[must_use]
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::Database(_) | AppError::Config(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Returns a function that turns a failed query into a database error with the given message,
+    /// for use with `map_err()`.
+    pub fn database<E: std::fmt::Display>(message: &str) -> impl FnOnce(E) -> AppError + '_ {
+        move |error| AppError::Database(format!("{message}: {error}"))
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppError::NotFound(message)
+            | AppError::Conflict(message)
+            | AppError::Validation(message)
+            | AppError::Database(message)
+            | AppError::Config(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<diesel::result::Error> for AppError {
+    fn from(error: diesel::result::Error) -> Self {
+        match error {
+            diesel::result::Error::NotFound => AppError::NotFound(error.to_string()),
+            error => AppError::Database(error.to_string()),
+        }
+    }
+}
+
+impl From<diesel::r2d2::PoolError> for AppError {
+    fn from(error: diesel::r2d2::PoolError) -> Self {
+        AppError::Database(format!("Could not get a database connection: {error}"))
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let body = serde_json::json!({ "error": self.to_string() }).to_string();
+        (self.status_code(), Response::new(Body::from(body))).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_map_to_status_codes() {
+        let cases = [
+            (AppError::NotFound("a".into()), StatusCode::NOT_FOUND),
+            (AppError::Conflict("a".into()), StatusCode::CONFLICT),
+            (
+                AppError::Validation("a".into()),
+                StatusCode::UNPROCESSABLE_ENTITY,
+            ),
+            (
+                AppError::Database("a".into()),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (
+                AppError::Config("a".into()),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        ];
+        for (error, status) in cases {
+            assert_eq!(error.status_code(), status);
+            assert_eq!(error.into_response().status(), status);
+        }
+    }
+
+    #[test]
+    fn diesel_errors_are_converted() {
+        assert!(matches!(
+            AppError::from(diesel::result::Error::NotFound),
+            AppError::NotFound(_)
+        ));
+        assert!(matches!(
+            AppError::from(diesel::result::Error::RollbackTransaction),
+            AppError::Database(_)
+        ));
+        assert_eq!(
+            AppError::database("Error loading adjustments")(diesel::result::Error::NotFound),
+            AppError::Database("Error loading adjustments: Record not found".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn response_contains_the_message() {
+        let response = AppError::Conflict("In use".to_string()).into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], br#"{"error":"In use"}"#);
+    }
+}
@@ -0,0 +1,138 @@
+use serde::{Deserialize, Deserializer, Serializer};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether IDs are serialized as strings, e.g. `"id": "12345"`.
+///
+/// JavaScript clients lose precision on numbers beyond 2^53, which IDs can reach in long-lived
+/// deployments. This is off by default, since existing clients expect numbers. IDs are always
+/// accepted both as numbers and as strings.
+static IDS_AS_STRINGS: AtomicBool = AtomicBool::new(false);
+
+#[cfg(test)]
+thread_local! {
+    // Lets a test serialize IDs as strings without affecting the tests that run in parallel.
+    static IDS_AS_STRINGS_OVERRIDE: std::cell::Cell<Option<bool>> =
+        const { std::cell::Cell::new(None) };
+}
+
+/// Sets whether IDs are serialized as strings.
+pub fn set_ids_as_strings(enabled: bool) {
+    IDS_AS_STRINGS.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns whether IDs are serialized as strings.
+pub fn ids_as_strings() -> bool {
+    #[cfg(test)]
+    if let Some(enabled) = IDS_AS_STRINGS_OVERRIDE.get() {
+        return enabled;
+    }
+    IDS_AS_STRINGS.load(Ordering::Relaxed)
+}
+
+/// Runs the given function with IDs serialized as strings, on the current thread only.
+#[cfg(test)]
+pub fn with_ids_as_strings<T>(f: impl FnOnce() -> T) -> T {
+    IDS_AS_STRINGS_OVERRIDE.set(Some(true));
+    let result = f();
+    IDS_AS_STRINGS_OVERRIDE.set(None);
+    result
+}
+
+/// Serializes an ID in the configured format. Use with `#[serde(with = "crate::id")]`.
+#[allow(clippy::trivially_copy_pass_by_ref)]
+pub fn serialize<S: Serializer>(id: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+    serialize_as(*id, ids_as_strings(), serializer)
+}
+
+/// Serializes an optional ID in the configured format. Use with
+/// `#[serde(serialize_with = "crate::id::serialize_option")]`.
+#[allow(clippy::ref_option)]
+pub fn serialize_option<S: Serializer>(id: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+    match id {
+        Some(id) => serialize_as(*id, ids_as_strings(), serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn serialize_as<S: Serializer>(id: u64, as_string: bool, serializer: S) -> Result<S::Ok, S::Error> {
+    if as_string {
+        serializer.serialize_str(&id.to_string())
+    } else {
+        serializer.serialize_u64(id)
+    }
+}
+
+/// Deserializes an ID that is given either as a number or as a string.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawId {
+        Number(u64),
+        String(String),
+    }
+
+    match RawId::deserialize(deserializer)? {
+        RawId::Number(id) => Ok(id),
+        RawId::String(id) => id
+            .parse()
+            .map_err(|_| serde::de::Error::custom(format!("invalid ID: {id}"))),
+    }
+}
+
+/// Deserializes a list of IDs that are given either as numbers or as strings. Use with
+/// `#[serde(deserialize_with = "crate::id::deserialize_vec")]`.
+pub fn deserialize_vec<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u64>, D::Error> {
+    Ok(Vec::<Id>::deserialize(deserializer)?
+        .into_iter()
+        .map(|Id(id)| id)
+        .collect())
+}
+
+/// An ID that is serialized according to the configured format, for use in manual `Serialize`
+/// implementations. It can be deserialized from a number or a string.
+#[derive(Deserialize)]
+pub struct Id(#[serde(deserialize_with = "deserialize")] pub u64);
+
+impl serde::Serialize for Id {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize(&self.0, serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn ids_are_serialized_as_numbers_or_strings() {
+        assert_eq!(
+            serialize_as(u64::MAX, false, serde_json::value::Serializer).unwrap(),
+            json!(u64::MAX)
+        );
+        assert_eq!(
+            serialize_as(u64::MAX, true, serde_json::value::Serializer).unwrap(),
+            json!("18446744073709551615")
+        );
+    }
+
+    #[test]
+    fn models_serialize_ids_as_strings_when_enabled() {
+        let result = crate::models::BatchDeleteResult {
+            id: 12345,
+            status: crate::models::DeleteStatus::Deleted,
+        };
+        assert_eq!(serde_json::to_value(&result).unwrap()["id"], json!(12345));
+        let value = with_ids_as_strings(|| serde_json::to_value(&result).unwrap());
+        assert_eq!(value["id"], json!("12345"));
+    }
+
+    #[test]
+    fn ids_are_deserialized_from_numbers_and_strings() {
+        assert_eq!(deserialize(json!(12345)).unwrap(), 12345);
+        assert_eq!(deserialize(json!("12345")).unwrap(), 12345);
+        assert!(deserialize(json!("abc")).is_err());
+        assert!(deserialize(json!(-1)).is_err());
+        assert_eq!(deserialize_vec(json!([1, "2"])).unwrap(), vec![1, 2]);
+    }
+}
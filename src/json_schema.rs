@@ -0,0 +1,230 @@
+use crate::models::{NewTimeEntry, MAX_ADJUSTMENT, MAX_VARCHAR_LENGTH};
+use serde_json::{json, Value};
+
+/// The resources that have a JSON Schema for their request body.
+pub const RESOURCES: [&str; 3] = ["adjustment", "adjustment-type", "time-entry"];
+
+/// Returns the JSON Schema of the request body to create the given resource, or `None` if the
+/// resource is unknown.
+///
+/// The schemas describe the fields of `NewAdjustment`, `NewAdjustmentType` and `NewTimeEntry` as
+/// they are deserialized, including the limits that are checked when validating them. The tests
+/// check that the properties match the fields that serde deserializes.
+#[must_use]
+pub fn for_resource(resource: &str) -> Option<Value> {
+    let (title, properties, required) = match resource {
+        "adjustment" => (
+            "New adjustment",
+            json!({
+                "type": {
+                    "description": "The ID of the adjustment type, as a number or a string.",
+                    "type": ["integer", "string"],
+                    "minimum": 1,
+                    "pattern": "^[1-9][0-9]*$",
+                },
+                "comment": {
+                    "description": "The maximum length depends on the max_comment_length setting.",
+                    "type": ["string", "null"],
+                    "maxLength": MAX_VARCHAR_LENGTH,
+                },
+                "created": created(),
+            }),
+            json!(["type"]),
+        ),
+        "adjustment-type" => (
+            "New adjustment type",
+            json!({
+                "description": {
+                    "type": "string",
+                    "minLength": 1,
+                    "maxLength": MAX_VARCHAR_LENGTH,
+                },
+                "adjustment": {
+                    "description": "The number of minutes that are added or removed.",
+                    "type": "integer",
+                    "minimum": -MAX_ADJUSTMENT,
+                    "maximum": MAX_ADJUSTMENT,
+                },
+                "sort_order": {
+                    "type": ["integer", "null"],
+                },
+                "expires_in_days": {
+                    "description": "Rewards are ignored when they are older than this.",
+                    "type": ["integer", "null"],
+                    "minimum": 0,
+                    "maximum": u16::MAX,
+                },
+                "color": {
+                    "type": ["string", "null"],
+                    "pattern": "^#[0-9a-fA-F]{6}$",
+                },
+            }),
+            json!(["description", "adjustment"]),
+        ),
+        "time-entry" => (
+            "New time entry",
+            json!({
+                "time": {
+                    "description": "The screen time, in minutes.",
+                    "type": "integer",
+                    "minimum": 0,
+                    "maximum": NewTimeEntry::MAX_TIME,
+                },
+                "created": created(),
+                "label": {
+                    "type": ["string", "null"],
+                    "maxLength": MAX_VARCHAR_LENGTH,
+                },
+            }),
+            json!(["time"]),
+        ),
+        _ => return None,
+    };
+
+    Some(json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": title,
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    }))
+}
+
+/// The schema of an optional creation date. Dates are in UTC, without a timezone.
+fn created() -> Value {
+    json!({
+        "description": "The creation date in UTC, e.g. 2023-01-01T12:00:00. Defaults to now.",
+        "type": ["string", "null"],
+        "pattern": "^\\d{4}-\\d{2}-\\d{2}T\\d{2}:\\d{2}:\\d{2}(\\.\\d+)?$",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{NewAdjustment, NewAdjustmentType};
+    use serde::de::{self, Deserialize, Visitor};
+
+    // A deserializer that records the names of the fields of the struct that is deserialized.
+    struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> de::Deserializer<'de> for FieldNames<'_> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("expected a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(de::Error::custom("only the field names are needed"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+            option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+            ignored_any
+        }
+    }
+
+    // Returns the sorted names of the fields that serde deserializes for the given struct.
+    fn struct_fields<'de, T: Deserialize<'de>>() -> Vec<&'static str> {
+        let mut fields: &'static [&'static str] = &[];
+        assert!(T::deserialize(FieldNames(&mut fields)).is_err());
+        let mut fields = fields.to_vec();
+        fields.sort_unstable();
+        fields
+    }
+
+    // Returns the sorted names of the properties of the schema.
+    fn schema_properties(schema: &Value) -> Vec<&str> {
+        let mut properties = schema["properties"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        properties.sort_unstable();
+        properties
+    }
+
+    // Returns an object with the required properties of the schema, set to the given value.
+    fn required_fields(schema: &Value, value: &Value) -> Value {
+        schema["required"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|field| (field.as_str().unwrap().to_string(), value.clone()))
+            .collect::<serde_json::Map<_, _>>()
+            .into()
+    }
+
+    #[test]
+    fn required_fields_match_the_structs() {
+        let schema = for_resource("adjustment").unwrap();
+        serde_json::from_value::<NewAdjustment>(required_fields(&schema, &json!(1))).unwrap();
+
+        let schema = for_resource("time-entry").unwrap();
+        serde_json::from_value::<NewTimeEntry>(required_fields(&schema, &json!(60))).unwrap();
+
+        let schema = for_resource("adjustment-type").unwrap();
+        let mut body = required_fields(&schema, &json!(15));
+        body["description"] = json!("Cleaned room");
+        serde_json::from_value::<NewAdjustmentType>(body).unwrap();
+    }
+
+    #[test]
+    fn properties_match_the_structs() {
+        let schema = for_resource("adjustment").unwrap();
+        assert_eq!(schema_properties(&schema), struct_fields::<NewAdjustment>());
+
+        let schema = for_resource("adjustment-type").unwrap();
+        assert_eq!(
+            schema_properties(&schema),
+            struct_fields::<NewAdjustmentType>()
+        );
+
+        let schema = for_resource("time-entry").unwrap();
+        assert_eq!(schema_properties(&schema), struct_fields::<NewTimeEntry>());
+    }
+
+    #[test]
+    fn missing_required_fields_are_rejected() {
+        for (resource, valid_body) in [
+            ("adjustment", json!({ "type": 1 })),
+            (
+                "adjustment-type",
+                json!({ "description": "Cleaned room", "adjustment": 15 }),
+            ),
+            ("time-entry", json!({ "time": 60 })),
+        ] {
+            let schema = for_resource(resource).unwrap();
+            for field in schema["required"].as_array().unwrap() {
+                let mut body = valid_body.clone();
+                body.as_object_mut()
+                    .unwrap()
+                    .remove(field.as_str().unwrap());
+                let result = match resource {
+                    "adjustment" => serde_json::from_value::<NewAdjustment>(body).err(),
+                    "adjustment-type" => serde_json::from_value::<NewAdjustmentType>(body).err(),
+                    _ => serde_json::from_value::<NewTimeEntry>(body).err(),
+                };
+                assert!(result.is_some(), "{resource} without {field} was accepted");
+            }
+        }
+    }
+
+    #[test]
+    fn all_resources_have_a_schema() {
+        for resource in RESOURCES {
+            let schema = for_resource(resource).unwrap();
+            assert_eq!(schema["type"], "object");
+        }
+        assert_eq!(for_resource("setting"), None);
+    }
+}
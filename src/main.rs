@@ -1,157 +1,911 @@
 use crate::db::AdjustmentQueryFilter;
+use crate::error::AppError;
+use crate::models::{
+    AdjustmentSign, AdjustmentTypeChangeset, Export, ImportedAdjustment, NewAdjustment,
+    NewAdjustmentType, NewTimeEntry, Source, TableDiff, TimeFormat,
+};
+use chrono::TimeZone;
+use chrono_tz::Tz;
 use clap::{Parser, Subcommand};
-use diesel::MysqlConnection;
+use diesel::{Connection, MysqlConnection};
+use output::{OutputFormat, OutputWriter, Rows};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 use tabled::settings::Style;
 
+mod balance;
 mod db;
+mod error;
+mod id;
+mod json_schema;
 pub mod models;
+mod output;
+mod schedule;
 pub mod schema;
 mod web;
+mod webhook;
 
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
 
-    let pool = db::get_connection_pool();
-    let connection = &mut pool.get().unwrap();
+    if run_command_without_database(cli.command.as_ref()) {
+        return;
+    }
+
+    DEBUG.store(cli.verbose > 0, Ordering::Relaxed);
+    let start = Instant::now();
+    let pool = db::get_connection_pool().unwrap_or_else(|e| exit_with_error(&e.to_string()));
+    let connection = &mut pool.get().or_exit();
+    if cli.timing {
+        TIMING.set((start, start.elapsed())).ok();
+    }
+    let quiet = cli.quiet;
 
-    // Todo: Return an exit code if the command failed.
     match &cli.command {
-        None => {}
-        Some(Commands::AdjustmentType { command }) => match command {
-            Some(AdjustmentTypeCommands::List { limit }) => {
-                list_adjustment_types(connection, *limit);
-            }
-            Some(AdjustmentTypeCommands::Add {
-                description,
-                adjustment,
-            }) => {
-                db::add_adjustment_type(connection, description.clone(), *adjustment);
+        None | Some(Commands::Diff { .. } | Commands::GenerateEnv) => {}
+        Some(Commands::AdjustmentType { command }) => {
+            run_adjustment_type_command(connection, command.as_ref(), quiet);
+        }
+        Some(Commands::Adjustment { command }) => {
+            run_adjustment_command(connection, command.as_ref(), quiet);
+        }
+        Some(Commands::Config { command }) => {
+            run_config_command(connection, command.as_ref(), quiet);
+        }
+        Some(Commands::Bench {
+            iterations,
+            adjustments,
+        }) => {
+            bench(connection, *iterations, *adjustments);
+        }
+        Some(Commands::Checkpoint { prune }) => checkpoint(connection, *prune, quiet),
+        Some(Commands::Correct { target }) => {
+            let delta = db::correct_adjusted_time(connection, *target).or_exit();
+            print_confirmation(quiet, &format!("Applied a correction of {delta:+} minutes"));
+        }
+        Some(Commands::Init { minutes }) => {
+            db::init_balance(connection, *minutes).or_exit();
+            print_confirmation(quiet, "Initialized the balance");
+            print_adjusted_time(connection, TimeFormat::default(), false);
+        }
+        Some(Commands::Export) => {
+            print_export(connection);
+        }
+        Some(Commands::Restore { file }) => {
+            restore(connection, file, quiet);
+        }
+        Some(Commands::ImportEvents { file }) => {
+            import_events(connection, file, quiet);
+        }
+        Some(Commands::ValidateEvents { file }) => validate_events(connection, file),
+        Some(Commands::Reset { yes }) => {
+            if *yes {
+                db::reset(connection).or_exit();
+                print_confirmation(quiet, "Deleted all data");
+            } else {
+                exit_with_error("This deletes all adjustments, adjustment types and time entries. Pass --yes to confirm.");
             }
-            Some(AdjustmentTypeCommands::Delete { id }) => {
-                let result = db::delete_adjustment_type(connection, *id);
-                match result {
-                    Ok(rows_deleted) => println!("Deleted {rows_deleted} adjustment type(s)"),
-                    Err(e) => println!("Error: {e}"),
+        }
+        Some(Commands::MigrationStatus) => print_migration_status(connection),
+        // Either a date or `--unreachable` is required, so if there is no date the unreachable
+        // adjustments are pruned.
+        Some(Commands::Prune {
+            before, dry_run, ..
+        }) => {
+            prune(connection, *before, *dry_run, quiet);
+        }
+        Some(Commands::Report { days }) => {
+            print_report(connection, *days);
+        }
+        Some(Commands::Serve) => web::serve().await,
+        Some(Commands::Simulate { script, floor }) => simulate(script, *floor),
+        Some(Commands::Verify) => verify(connection),
+        Some(Commands::Stats { output }) => {
+            print_adjustment_stats(connection, output.writer().as_ref());
+        }
+        Some(Commands::Time {
+            time_format,
+            explain_sql,
+            verbose,
+            export,
+        }) => {
+            if *explain_sql {
+                for sql in db::get_adjusted_time_sql(connection).or_exit() {
+                    eprintln!("{sql}");
                 }
             }
-            None => {}
-        },
-        Some(Commands::Adjustment { command }) => match command {
-            None => {}
-            Some(AdjustmentCommands::List {
-                limit,
-                adjustment_type_id,
-                since,
-            }) => {
-                list_adjustments(
-                    connection,
-                    &AdjustmentQueryFilter {
-                        limit: *limit,
-                        atid: *adjustment_type_id,
-                        since: since.map(|d| d.and_hms_opt(0, 0, 0).unwrap()),
-                    },
-                );
+            if let Some(name) = export {
+                println!("{name}={}", db::get_adjusted_time(connection).or_exit());
+            } else {
+                print_adjusted_time(connection, *time_format, *verbose);
             }
-            Some(AdjustmentCommands::Add {
-                adjustment_type_id,
-                comment,
-            }) => {
-                add_adjustment(connection, *adjustment_type_id, comment);
+        }
+        Some(Commands::TopAdjustments { limit, sign }) => {
+            let mut table =
+                tabled::Table::new(db::get_top_adjustments(connection, *limit, *sign).or_exit());
+            table.with(Style::sharp());
+            println!("{table}");
+        }
+        Some(Commands::TimeEntry { command }) => {
+            run_time_entry_command(connection, command.as_ref(), quiet);
+        }
+    }
+
+    print_timing();
+}
+
+/// Compares the screen time with a recalculation from the adjustments selected by the database, and
+/// exits with an error if they disagree.
+fn verify(connection: &mut MysqlConnection) {
+    let check = db::check_balance(connection).or_exit();
+    println!("Screen time: {} minutes", check.fold);
+    println!("Recalculated: {} minutes", check.recalculated);
+    println!("Sum in the database: {} minutes", check.aggregate);
+    if check.floor_correction() > 0 {
+        println!(
+            "The balance floor raised the screen time by {} minutes above the sum",
+            check.floor_correction()
+        );
+    }
+    if !check.is_consistent() {
+        exit_with_error("The calculations disagree, the data may be inconsistent");
+    }
+}
+
+/// Runs the given command if it doesn't require a database. Returns whether the command was run.
+fn run_command_without_database(command: Option<&Commands>) -> bool {
+    match command {
+        // Comparing exports only works on files.
+        Some(Commands::Diff { a, b }) => diff_exports(a, b),
+        // Generating a .env file is useful to set up a new instance, which may not have a database
+        // yet. The current values are filled in if the database can be reached.
+        Some(Commands::GenerateEnv) => {
+            let pool = db::get_connection_pool().ok();
+            let mut connection = pool.as_ref().and_then(|pool| pool.get().ok());
+            print!("{}", generate_env(connection.as_deref_mut()));
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// When the command started and how long connecting to the database took, if `--timing` is given.
+static TIMING: OnceLock<(Instant, Duration)> = OnceLock::new();
+
+/// Prints how long connecting to the database and running the command took, if `--timing` is
+/// given. This is printed to stderr so it doesn't mix with the output of the command.
+fn print_timing() {
+    if let Some((start, connected)) = TIMING.get() {
+        let total = start.elapsed();
+        eprintln!(
+            "Database connection: {connected:.2?}, command: {:.2?}, total: {total:.2?}",
+            total.saturating_sub(*connected)
+        );
+    }
+}
+
+/// Runs the given adjustment type subcommand.
+fn run_adjustment_type_command(
+    connection: &mut MysqlConnection,
+    command: Option<&AdjustmentTypeCommands>,
+    quiet: bool,
+) {
+    match command {
+        Some(AdjustmentTypeCommands::List {
+            limit,
+            deletable,
+            output,
+        }) => {
+            list_adjustment_types(connection, *limit, *deletable, output.writer().as_ref());
+        }
+        Some(AdjustmentTypeCommands::Add {
+            description,
+            adjustment,
+            sort_order,
+            expires_in_days,
+            color,
+        }) => {
+            let new_adjustment_type = NewAdjustmentType {
+                description: description.clone(),
+                adjustment: *adjustment,
+                sort_order: *sort_order,
+                expires_in_days: *expires_in_days,
+                color: color.clone(),
+            };
+            let rows_inserted =
+                db::insert_adjustment_type(connection, &new_adjustment_type).or_exit();
+            print_confirmation(quiet, &format!("Added {rows_inserted} adjustment type(s)"));
+        }
+        Some(AdjustmentTypeCommands::Update {
+            id,
+            description,
+            adjustment,
+            sort_order,
+            expires_in_days,
+            color,
+        }) => {
+            let changes = AdjustmentTypeChangeset {
+                description: description.clone(),
+                adjustment: *adjustment,
+                sort_order: *sort_order,
+                expires_in_days: *expires_in_days,
+                color: color.clone(),
+            };
+            let rows_updated = db::update_adjustment_type(connection, *id, &changes).or_exit();
+            print_confirmation(quiet, &format!("Updated {rows_updated} adjustment type(s)"));
+        }
+        Some(AdjustmentTypeCommands::Delete { id }) => {
+            let rows_deleted = db::delete_adjustment_type(connection, *id).or_exit();
+            print_confirmation(quiet, &format!("Deleted {rows_deleted} adjustment type(s)"));
+        }
+        None => {}
+    }
+}
+
+/// Runs the given adjustment subcommand.
+fn run_adjustment_command(
+    connection: &mut MysqlConnection,
+    command: Option<&AdjustmentCommands>,
+    quiet: bool,
+) {
+    match command {
+        None => {}
+        Some(AdjustmentCommands::List {
+            limit,
+            adjustment_type_id,
+            since,
+            since_last_entry,
+            tz,
+            output,
+        }) => {
+            list_adjustments(
+                connection,
+                &AdjustmentQueryFilter {
+                    limit: *limit,
+                    atid: *adjustment_type_id,
+                    since: since.map(|d| d.and_hms_opt(0, 0, 0).unwrap()),
+                    since_last_entry: *since_last_entry,
+                    ..Default::default()
+                },
+                *tz,
+                output.writer().as_ref(),
+            );
+        }
+        Some(AdjustmentCommands::Add {
+            adjustment_type_id,
+            comment,
+            created,
+            allow_future,
+        }) => {
+            let new_adjustment = NewAdjustment {
+                adjustment_type_id: *adjustment_type_id,
+                comment: comment.clone(),
+                created: *created,
+                source: Source::Cli,
+            };
+            match add_adjustment(connection, &new_adjustment, *allow_future) {
+                Ok(rows_inserted) => {
+                    print_confirmation(quiet, &format!("Added {rows_inserted} adjustment(s)"));
+                }
+                Err(e) => exit_with_error(&e.to_string()),
             }
-            Some(AdjustmentCommands::Delete { id }) => {
-                db::delete_adjustment(connection, *id);
+        }
+        Some(AdjustmentCommands::Delete { id }) => {
+            let rows_deleted = db::delete_adjustment(connection, *id).or_exit();
+            print_confirmation(quiet, &format!("Deleted {rows_deleted} adjustment(s)"));
+        }
+        Some(AdjustmentCommands::Undo) => match db::undo_last_adjustment(connection).or_exit() {
+            Some(adjustment) => {
+                print_confirmation(quiet, &format!("Undid adjustment {}", adjustment.id));
+                print_adjusted_time(connection, TimeFormat::default(), false);
             }
+            None => exit_with_error("There are no adjustments to undo"),
         },
-        Some(Commands::Serve) => web::serve().await,
-        Some(Commands::Time) => {
-            print_adjusted_time(connection);
+    }
+}
+
+/// Runs the given config subcommand.
+fn run_config_command(
+    connection: &mut MysqlConnection,
+    command: Option<&ConfigCommands>,
+    quiet: bool,
+) {
+    match command {
+        None => {}
+        Some(ConfigCommands::List) => {
+            let mut table = tabled::Table::new(db::get_settings(connection).or_exit());
+            table.with(Style::sharp());
+            println!("{table}");
         }
-        Some(Commands::TimeEntry { command }) => match command {
-            None => {}
-            Some(TimeEntryCommands::Current) => {
-                print_current_time_entry(connection);
-            }
-            Some(TimeEntryCommands::List { limit }) => {
-                list_time_entries(connection, *limit);
+        Some(ConfigCommands::Get { name }) => {
+            if let Some(value) = db::get_setting(connection, name).or_exit() {
+                println!("{value}");
             }
-            Some(TimeEntryCommands::Add { time }) => {
-                db::add_time_entry(connection, *time, None);
-            }
-            Some(TimeEntryCommands::Delete { id }) => {
-                db::delete_time_entry(connection, *id);
+        }
+        Some(ConfigCommands::Set { name, value }) => {
+            db::set_setting(connection, name, value).or_exit();
+            print_confirmation(quiet, &format!("Set {name} to {value}"));
+        }
+    }
+}
+
+/// Runs the given time entry subcommand.
+fn run_time_entry_command(
+    connection: &mut MysqlConnection,
+    command: Option<&TimeEntryCommands>,
+    quiet: bool,
+) {
+    match command {
+        None => {}
+        Some(TimeEntryCommands::Current { json }) => {
+            print_current_time_entry(connection, *json);
+        }
+        Some(TimeEntryCommands::List {
+            limit,
+            context,
+            tz,
+            output,
+        }) => {
+            list_time_entries(connection, *limit, *context, *tz, output.writer().as_ref());
+        }
+        Some(TimeEntryCommands::Add {
+            time,
+            label,
+            created,
+            force,
+            allow_future,
+        }) => {
+            let new_time_entry = NewTimeEntry {
+                time: *time,
+                created: *created,
+                label: label.clone(),
+                source: Source::Cli,
+            };
+            match db::insert_time_entry(connection, &new_time_entry, *force, *allow_future) {
+                Ok(rows_inserted) => {
+                    print_confirmation(quiet, &format!("Added {rows_inserted} time entry(s)"));
+                }
+                Err(e) => exit_with_error(&e.to_string()),
             }
-        },
+        }
+        Some(TimeEntryCommands::Delete { id }) => {
+            let rows_deleted = db::delete_time_entry(connection, *id).or_exit();
+            print_confirmation(quiet, &format!("Deleted {rows_deleted} time entry(s)"));
+        }
     }
 }
 
-/// Lists the available adjustments.
-fn list_adjustments(connection: &mut MysqlConnection, filter: &AdjustmentQueryFilter) {
-    let results = db::get_adjustments(connection, filter);
+/// Parses a color passed on the command line, rejecting anything that is not a hex code.
+fn parse_color(color: &str) -> Result<String, String> {
+    if models::is_valid_color(color) {
+        Ok(color.to_string())
+    } else {
+        Err("the color must be a hex code like #00ff00".to_string())
+    }
+}
 
-    // Output results as a table.
-    let mut table = tabled::Table::new(results);
-    table.with(Style::sharp());
-    println!("{table}");
+/// Parses an adjustment value passed on the command line, rejecting values of more than a day.
+fn parse_adjustment(adjustment: &str) -> Result<i32, String> {
+    let max = models::MAX_ADJUSTMENT;
+    adjustment
+        .parse()
+        .ok()
+        .filter(|adjustment: &i32| adjustment.abs() <= max)
+        .ok_or_else(|| {
+            format!("the adjustment must be a number of minutes between -{max} and {max}")
+        })
+}
+
+/// Parses the name of a shell variable, which may only contain letters, digits and underscores and
+/// can't start with a digit.
+fn parse_variable_name(name: &str) -> Result<String, String> {
+    let valid = name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && name.chars().next().is_some_and(|c| !c.is_ascii_digit());
+    if valid {
+        Ok(name.to_string())
+    } else {
+        Err("the name may only contain letters, digits and underscores, and can't start with a digit".to_string())
+    }
+}
+
+/// Whether debug output is printed, enabled with `--verbose`.
+static DEBUG: AtomicBool = AtomicBool::new(false);
+
+/// Prints a debug message to stderr if debug output is enabled. The message is only built when it
+/// is printed.
+pub fn debug(message: impl FnOnce() -> String) {
+    if DEBUG.load(Ordering::Relaxed) {
+        eprintln!("DEBUG {}", message());
+    }
+}
+
+/// Prints the given error and exits with a non-zero exit code.
+pub fn exit_with_error(message: &str) -> ! {
+    eprintln!("Error: {message}");
+    print_timing();
+    std::process::exit(1);
+}
+
+/// Unwraps a result, or prints the error and exits with a non-zero exit code.
+trait OrExit<T> {
+    fn or_exit(self) -> T;
+}
+
+impl<T, E: std::fmt::Display> OrExit<T> for Result<T, E> {
+    fn or_exit(self) -> T {
+        self.unwrap_or_else(|e| exit_with_error(&e.to_string()))
+    }
+}
+
+/// Lists the database migrations and whether they have been applied. Exits with an error if any
+/// migration is pending, so this can be used to check a deployment before starting the server.
+fn print_migration_status(connection: &mut MysqlConnection) {
+    let migrations = db::get_migration_status(connection).or_exit();
+    for (name, applied) in &migrations {
+        let status = if *applied { "applied" } else { "pending" };
+        println!("[{status}] {name}");
+    }
+    let pending = migrations.iter().filter(|(_, applied)| !applied).count();
+    if pending > 0 {
+        exit_with_error(&format!(
+            "{pending} migration(s) pending, apply them with `diesel migration run`"
+        ));
+    }
+}
+
+/// Prints a confirmation message, unless the output should be kept quiet.
+fn print_confirmation(quiet: bool, message: &str) {
+    if !quiet {
+        println!("{message}");
+    }
+}
+
+/// Returns the timezone in which dates are shown: the given one, or else the `timezone` setting.
+fn display_timezone(
+    connection: &mut MysqlConnection,
+    tz: Option<Tz>,
+) -> Result<Option<Tz>, AppError> {
+    if tz.is_some() {
+        return Ok(tz);
+    }
+    let Some(setting) = db::get_setting(connection, "timezone")? else {
+        return Ok(None);
+    };
+    let tz = setting.parse().ok();
+    if tz.is_none() {
+        eprintln!("Ignoring the invalid timezone setting {setting}");
+    }
+    Ok(tz)
+}
+
+/// Converts a date as stored in the database, in UTC, to the given timezone.
+fn to_timezone(date: chrono::NaiveDateTime, tz: Option<Tz>) -> chrono::NaiveDateTime {
+    tz.map_or(date, |tz| tz.from_utc_datetime(&date).naive_local())
+}
+
+/// Lists the available adjustments.
+fn list_adjustments(
+    connection: &mut MysqlConnection,
+    filter: &AdjustmentQueryFilter,
+    tz: Option<Tz>,
+    writer: &dyn OutputWriter,
+) {
+    let tz = display_timezone(connection, tz).or_exit();
+    let mut results = db::get_adjustments(connection, filter).or_exit();
+    for adjustment in &mut results {
+        adjustment.created = to_timezone(adjustment.created, tz);
+    }
+    writer.write(&Rows::new(&results));
 }
 
 /// Adds an adjustment.
+/// Returns the number of inserted rows.
 fn add_adjustment(
     connection: &mut MysqlConnection,
-    adjustment_type_id: u64,
-    comment: &Option<String>,
-) {
-    let adjustment_type = db::get_adjustment_types(connection, None)
-        .into_iter()
-        .find(|at| at.id == adjustment_type_id)
-        .expect("Adjustment type not found");
+    new_adjustment: &NewAdjustment,
+    allow_future: bool,
+) -> Result<usize, AppError> {
+    let adjustment_type_id = new_adjustment.adjustment_type_id;
+    if !db::get_adjustment_types(connection, None)?
+        .iter()
+        .any(|at| at.id == adjustment_type_id)
+    {
+        return Err(AppError::NotFound(format!(
+            "Adjustment type {adjustment_type_id} not found"
+        )));
+    }
 
-    db::add_adjustment(connection, &adjustment_type, comment, &None);
+    Ok(db::insert_adjustment(
+        connection,
+        new_adjustment,
+        allow_future,
+    )?)
 }
 
-/// Lists the available adjustment types.
-fn list_adjustment_types(connection: &mut MysqlConnection, limit: Option<u8>) {
-    let results = db::get_adjustment_types(connection, limit);
-
-    // Output results as a table.
-    let mut table = tabled::Table::new(results);
-    table.with(Style::sharp());
-    println!("{table}");
+/// Lists the available adjustment types, or only the ones without adjustments.
+fn list_adjustment_types(
+    connection: &mut MysqlConnection,
+    limit: Option<u8>,
+    deletable: bool,
+    writer: &dyn OutputWriter,
+) {
+    let filter = db::AdjustmentTypeQueryFilter {
+        limit,
+        deletable,
+        ..Default::default()
+    };
+    let results = db::get_filtered_adjustment_types(connection, &filter).or_exit();
+    writer.write(&Rows::new(&results));
 }
 
 /// Prints the current, adjusted time.
 ///
 /// This calculates the current time by taking the most recent time entry and adding all adjustments
-/// to it.
-fn print_adjusted_time(connection: &mut MysqlConnection) {
-    let adjusted_time = db::get_adjusted_time(connection);
-    println!("{:01}:{:02}", adjusted_time / 60, adjusted_time % 60);
+/// to it. When verbose, this also tells whether an initial balance has been set.
+fn print_adjusted_time(connection: &mut MysqlConnection, time_format: TimeFormat, verbose: bool) {
+    let balance = db::get_balance(connection).or_exit();
+    println!("{}", time_format.format(balance.time));
+    if verbose {
+        if balance.initialized {
+            println!("Initialized: yes");
+        } else {
+            println!("Initialized: no, add a time entry to set an initial balance");
+        }
+    }
 }
 
-/// Prints the current time.
-fn print_current_time_entry(connection: &mut MysqlConnection) {
-    let time_entry = db::get_current_time_entry(connection);
-    if let Some(time_entry) = time_entry {
-        println!("{time_entry}");
+/// Times the calculation of the adjusted time over a number of iterations.
+///
+/// The given number of adjustments is added before starting. This is done in a transaction that is
+/// rolled back afterwards, so the existing data is not affected.
+fn bench(connection: &mut MysqlConnection, iterations: u32, adjustments: u32) {
+    let mut durations = vec![];
+    let result = connection.transaction::<(), diesel::result::Error, _>(|connection| {
+        db::add_adjustment_type(connection, "Benchmark".to_string(), 1, None).or_exit();
+        let adjustment_type_id = db::get_adjustment_types(connection, None)
+            .or_exit()
+            .into_iter()
+            .filter(|at| at.description == "Benchmark")
+            .map(|at| at.id)
+            .max()
+            .unwrap();
+
+        // Spread the adjustments over the day after a time entry, so they all count.
+        let start = db::get_current_timestamp(connection).or_exit() - chrono::Duration::days(1);
+        db::add_time_entry(connection, 60, Some(start), true).or_exit();
+        let new_adjustments: Vec<NewAdjustment> = (0..adjustments)
+            .map(|i| NewAdjustment {
+                adjustment_type_id,
+                comment: None,
+                created: Some(start + chrono::Duration::seconds(i64::from(i % 86_400))),
+                source: Source::Cli,
+            })
+            .collect();
+        db::add_adjustments(connection, &new_adjustments).or_exit();
+
+        for _ in 0..iterations {
+            let start = Instant::now();
+            db::get_adjusted_time(connection).or_exit();
+            durations.push(start.elapsed());
+        }
+
+        Err(diesel::result::Error::RollbackTransaction)
+    });
+    if let Err(e) = result {
+        if e != diesel::result::Error::RollbackTransaction {
+            exit_with_error(&e.to_string());
+        }
     }
+
+    let min = durations.iter().min().unwrap();
+    let max = durations.iter().max().unwrap();
+    let avg = durations.iter().sum::<Duration>() / iterations;
+    println!("Iterations: {iterations}, adjustments: {adjustments}");
+    println!("Min: {min:?}, avg: {avg:?}, max: {max:?}");
 }
 
-/// Lists the available time entries.
-fn list_time_entries(connection: &mut MysqlConnection, limit: Option<u8>) {
-    let results = db::get_time_entries(connection, limit);
+/// The documented environment variables, with comments and example values.
+const ENV_TEMPLATE: &str = include_str!("../.env.dist");
 
-    // Output results as a table.
-    let mut table = tabled::Table::new(results);
+/// The environment variables that hold secrets, which are never printed.
+const SECRET_ENV_VARS: [&str; 2] = ["ADMIN_API_KEY", "WEBHOOK_URL"];
+
+/// Returns a .env template that documents every supported environment variable.
+///
+/// The template is based on `.env.dist`. Variables that have a value, either in the database or in
+/// the environment, are filled in and uncommented. Without a database connection only the
+/// environment is used. Secrets are replaced by a placeholder and the password in the database URL
+/// is redacted.
+fn generate_env(mut connection: Option<&mut MysqlConnection>) -> String {
+    ENV_TEMPLATE
+        .lines()
+        .map(|line| {
+            let Some((name, _)) = line.trim_start_matches('#').split_once('=') else {
+                return format!("{line}\n");
+            };
+            let is_variable = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+            let value = is_variable
+                .then(|| match connection.as_deref_mut() {
+                    Some(connection) => db::get_setting(connection, &name.to_lowercase()).or_exit(),
+                    None => std::env::var(name).ok().filter(|value| !value.is_empty()),
+                })
+                .flatten();
+            match value {
+                None => format!("{line}\n"),
+                Some(_) if SECRET_ENV_VARS.contains(&name) => format!("{name}=<secret>\n"),
+                Some(value) if name == "DATABASE_URL" => {
+                    format!("{name}={}\n", web::redact_database_url(&value))
+                }
+                Some(value) if value.contains(char::is_whitespace) => {
+                    format!("{name}=\"{value}\"\n")
+                }
+                Some(value) => format!("{name}={value}\n"),
+            }
+        })
+        .collect()
+}
+
+/// Prints an export of all data as JSON.
+fn print_export(connection: &mut MysqlConnection) {
+    let export = db::export(connection).or_exit();
+    println!("{}", serde_json::to_string_pretty(&export).unwrap());
+}
+
+/// Reads an export from the given file.
+fn read_export(file: &std::path::Path) -> Result<Export, String> {
+    std::fs::read_to_string(file)
+        .map_err(|e| format!("Could not read {}: {e}", file.display()))
+        .and_then(|json| {
+            serde_json::from_str::<Export>(&json).map_err(|e| format!("Invalid export: {e}"))
+        })
+}
+
+/// Prints the differences between two export files. Exits with an error if they differ.
+fn diff_exports(a: &std::path::Path, b: &std::path::Path) {
+    let (a, b) = match (read_export(a), read_export(b)) {
+        (Ok(a), Ok(b)) => (a, b),
+        (Err(e), _) | (_, Err(e)) => exit_with_error(&e),
+    };
+    let diffs = a.diff(&b);
+    let format_ids = |ids: &[u64]| {
+        ids.iter()
+            .map(u64::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    for diff in &diffs {
+        if diff.is_empty() {
+            println!("{}: no differences", diff.table);
+            continue;
+        }
+        println!(
+            "{}: {} added, {} removed, {} changed",
+            diff.table,
+            diff.added.len(),
+            diff.removed.len(),
+            diff.changed.len()
+        );
+        for (label, ids) in [
+            ("added", &diff.added),
+            ("removed", &diff.removed),
+            ("changed", &diff.changed),
+        ] {
+            if !ids.is_empty() {
+                println!("  {label}: {}", format_ids(ids));
+            }
+        }
+    }
+    if !diffs.iter().all(TableDiff::is_empty) {
+        exit_with_error("The exports differ");
+    }
+}
+
+/// Restores all data from the given export file.
+fn restore(connection: &mut MysqlConnection, file: &std::path::Path, quiet: bool) {
+    let export = read_export(file).map_err(AppError::Validation);
+    match export.and_then(|export| db::restore(connection, &export)) {
+        Ok(()) => print_confirmation(quiet, &format!("Restored {}", file.display())),
+        Err(e) => exit_with_error(&e.to_string()),
+    }
+}
+
+/// Imports the adjustments from the given JSON file.
+fn import_events(connection: &mut MysqlConnection, file: &std::path::Path, quiet: bool) {
+    let adjustments = std::fs::read_to_string(file)
+        .map_err(|e| format!("Could not read {}: {e}", file.display()))
+        .and_then(|json| {
+            serde_json::from_str::<Vec<ImportedAdjustment>>(&json)
+                .map_err(|e| format!("Invalid file: {e}"))
+        })
+        .map_err(AppError::Validation);
+    match adjustments.and_then(|adjustments| db::import_adjustments(connection, &adjustments)) {
+        Ok(count) => print_confirmation(quiet, &format!("Imported {count} adjustment(s)")),
+        Err(e) => exit_with_error(&e.to_string()),
+    }
+}
+
+/// Checks the adjustments in the given file without importing them, and reports all problems with
+/// the position of the adjustment in the file.
+fn validate_events(connection: &mut MysqlConnection, file: &std::path::Path) {
+    let records = std::fs::read_to_string(file)
+        .map_err(|e| format!("Could not read {}: {e}", file.display()))
+        .and_then(|json| {
+            serde_json::from_str::<Vec<serde_json::Value>>(&json)
+                .map_err(|e| format!("Invalid file: {e}"))
+        })
+        .unwrap_or_else(|e| exit_with_error(&e));
+
+    // Check the shape of each record first, and keep the position of the valid ones.
+    let mut problems = vec![];
+    let mut positions = vec![];
+    let mut adjustments = vec![];
+    for (i, record) in records.into_iter().enumerate() {
+        match serde_json::from_value::<ImportedAdjustment>(record) {
+            Ok(adjustment) => {
+                positions.push(i);
+                adjustments.push(adjustment);
+            }
+            Err(e) => problems.push((i, e.to_string())),
+        }
+    }
+    problems.extend(
+        db::validate_imported_adjustments(connection, &adjustments)
+            .or_exit()
+            .into_iter()
+            .map(|(i, problem)| (positions[i], problem)),
+    );
+    problems.sort_by_key(|(i, _)| *i);
+
+    for (i, problem) in &problems {
+        println!("Adjustment {}: {problem}", i + 1);
+    }
+    if !problems.is_empty() {
+        exit_with_error(&format!("Found {} problem(s)", problems.len()));
+    }
+    println!("All {} adjustment(s) are valid", positions.len());
+}
+
+/// Runs the events in the given script through the balance logic and prints the resulting
+/// timeline. The screen time doesn't go below the given floor.
+fn simulate(script: &std::path::Path, floor: i32) {
+    let events = std::fs::read_to_string(script)
+        .map_err(|e| format!("Could not read {}: {e}", script.display()))
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| format!("Invalid script: {e}")))
+        .or_exit();
+    let mut table = tabled::Table::new(balance::simulate(events, floor));
     table.with(Style::sharp());
     println!("{table}");
 }
 
+/// Adds a checkpoint, and optionally deletes the adjustments it supersedes.
+fn checkpoint(connection: &mut MysqlConnection, prune: bool, quiet: bool) {
+    match db::checkpoint(connection, prune) {
+        Ok((time, summary)) => {
+            print_confirmation(quiet, &format!("Added a checkpoint of {time} minutes"));
+            if prune {
+                print_confirmation(quiet, &format!("Deleted {} adjustment(s)", summary.count));
+            }
+        }
+        Err(e) => exit_with_error(&e.to_string()),
+    }
+}
+
+/// Deletes the adjustments that were created before the given date, or the unreachable adjustments
+/// if no date is given. In a dry run this reports which adjustments would be deleted.
+fn prune(
+    connection: &mut MysqlConnection,
+    before: Option<chrono::NaiveDate>,
+    dry_run: bool,
+    quiet: bool,
+) {
+    let summary = match before {
+        Some(before) => {
+            let before = before.and_hms_opt(0, 0, 0).unwrap();
+            db::prune_adjustments_before(connection, before, dry_run).or_exit()
+        }
+        None => db::prune_unreachable_adjustments(connection, dry_run).or_exit(),
+    };
+    let range = match (summary.first_id, summary.last_id) {
+        (Some(first_id), Some(last_id)) => format!(" (IDs {first_id} to {last_id})"),
+        _ => String::new(),
+    };
+    if dry_run {
+        println!("Would delete {} adjustment(s){range}", summary.count);
+    } else {
+        print_confirmation(
+            quiet,
+            &format!("Deleted {} adjustment(s){range}", summary.count),
+        );
+    }
+}
+
+/// Prints a summary of the adjustments made over the last given number of days.
+fn print_report(connection: &mut MysqlConnection, days: u16) {
+    let report = db::get_report(connection, days).or_exit();
+
+    println!("Report for the last {} day(s)", report.days);
+    println!("Total adjustments: {}", report.total_adjustments);
+    println!("Net minutes: {:+}", report.net_minutes);
+    match report.busiest_day {
+        Some(busiest_day) => println!("Busiest day: {busiest_day}"),
+        None => println!("Busiest day: -"),
+    }
+    match report.most_used_adjustment_type {
+        Some(adjustment_type) => println!(
+            "Most used adjustment type: {} ({})",
+            adjustment_type.description, adjustment_type.id
+        ),
+        None => println!("Most used adjustment type: -"),
+    }
+    if report.unreachable_adjustments > 0 {
+        println!(
+            "Unreachable adjustments: {} (delete them with `prune --unreachable`)",
+            report.unreachable_adjustments
+        );
+    }
+}
+
+/// Prints the number of adjustments per adjustment type, and their combined effect.
+fn print_adjustment_stats(connection: &mut MysqlConnection, writer: &dyn OutputWriter) {
+    let stats = db::get_adjustment_stats(connection).or_exit();
+    writer.write(&Rows::new(&stats));
+}
+
+/// Prints the current time, or the full time entry as JSON. If there is no time entry, the JSON
+/// output is `null`.
+fn print_current_time_entry(connection: &mut MysqlConnection, json: bool) {
+    let time_entry = db::get_current_time_entry(connection).or_exit();
+    if json {
+        println!("{}", serde_json::to_string(&time_entry).unwrap());
+    } else if let Some(time_entry) = time_entry {
+        println!("{time_entry}");
+    }
+}
+
+/// Lists the available time entries, optionally with the net adjustment that followed each entry.
+fn list_time_entries(
+    connection: &mut MysqlConnection,
+    limit: Option<u8>,
+    context: bool,
+    tz: Option<Tz>,
+    writer: &dyn OutputWriter,
+) {
+    let tz = display_timezone(connection, tz).or_exit();
+    if context {
+        let mut time_entries = db::get_time_entries_with_context(connection, limit).or_exit();
+        for time_entry in &mut time_entries {
+            time_entry.created = to_timezone(time_entry.created, tz);
+        }
+        writer.write(&Rows::new(&time_entries));
+    } else {
+        let mut time_entries = db::get_time_entries(connection, limit).or_exit();
+        for time_entry in &mut time_entries {
+            time_entry.created = to_timezone(time_entry.created, tz);
+        }
+        writer.write(&Rows::new(&time_entries));
+    }
+}
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None, arg_required_else_help = true)]
 struct Cli {
+    /// Prints debug output to stderr, like the inputs of every calculation of the screen time.
     #[arg(short, long, action = clap::ArgAction::Count)]
     verbose: u8,
 
+    /// Suppresses confirmation messages, only printing errors and requested data.
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Prints how long connecting to the database and running the command took to stderr.
+    #[arg(long, global = true)]
+    timing: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -168,15 +922,183 @@ enum Commands {
         #[command(subcommand)]
         command: Option<AdjustmentTypeCommands>,
     },
+    /// Commands related to settings.
+    Config {
+        #[command(subcommand)]
+        command: Option<ConfigCommands>,
+    },
+    /// Times the calculation of the screen time, using temporary data.
+    #[command(hide = true)]
+    Bench {
+        /// The number of times to calculate the screen time.
+        #[arg(short, long, default_value_t = 100, value_parser = clap::value_parser!(u32).range(1..))]
+        iterations: u32,
+        /// The number of adjustments to add before starting.
+        #[arg(short, long, default_value_t = 10_000)]
+        adjustments: u32,
+    },
+    /// Adds a time entry equal to the current screen time, so that the adjustments made so far no
+    /// longer need to be taken into account. The screen time doesn't change.
+    Checkpoint {
+        /// Deletes the adjustments that are superseded by the checkpoint.
+        #[arg(long)]
+        prune: bool,
+    },
+    /// Corrects the current screen time so that it matches the given time.
+    Correct {
+        /// The screen time to correct to, in minutes.
+        target: u16,
+    },
+    /// Compares two JSON exports and lists the rows that were added, removed or changed. Exits with
+    /// an error if the exports differ. This does not need a database connection.
+    Diff {
+        /// The path to the first export file.
+        a: std::path::PathBuf,
+        /// The path to the second export file.
+        b: std::path::PathBuf,
+    },
+    /// Exports all data as JSON.
+    Export,
+    /// Prints a .env template with all supported environment variables and their current values.
+    /// Secrets are replaced by a placeholder.
+    GenerateEnv,
+    /// Sets the initial screen time on a fresh install. Fails if there already are time entries.
+    Init {
+        /// The initial screen time, in minutes.
+        minutes: u16,
+    },
+    /// Replaces all data with the data from a JSON export.
+    Restore {
+        /// The path to the export file.
+        file: std::path::PathBuf,
+    },
+    /// Imports adjustments from a JSON file.
+    ///
+    /// The file contains a list of adjustments in the same shape as accepted by the API, e.g.
+    /// `[{"type": 1, "comment": "Cleaned room", "created": "2023-01-01T12:00:00"}]`. The type can
+    /// be an adjustment type ID or description. If any adjustment is invalid nothing is imported.
+    ImportEvents {
+        /// The path to the JSON file.
+        file: std::path::PathBuf,
+    },
+    /// Checks a JSON file of adjustments like `import-events` does, without importing anything.
+    /// All problems are reported, and the command exits with an error if there are any.
+    ValidateEvents {
+        /// The path to the JSON file.
+        file: std::path::PathBuf,
+    },
+    /// Deletes all adjustments, adjustment types and time entries. Settings are kept.
+    Reset {
+        /// Confirms that all data should be deleted.
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Lists the database migrations and whether they have been applied, without applying them.
+    /// Exits with an error code if any migration is pending.
+    MigrationStatus,
+    /// Deletes adjustments that were created before the given date, or that can't affect any
+    /// screen time. Adjustments that were created since the current time entry are always kept.
+    #[command(group(clap::ArgGroup::new("target").required(true)))]
+    Prune {
+        /// Deletes the adjustments that were created before this date.
+        #[arg(short, long, group = "target")]
+        before: Option<chrono::NaiveDate>,
+        /// Deletes the adjustments that were created before the earliest time entry, which can't
+        /// affect any screen time.
+        #[arg(long, group = "target")]
+        unreachable: bool,
+        /// Shows which adjustments would be deleted, without deleting them.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Prints a summary of the adjustments made over the last days.
+    Report {
+        /// The number of days to include in the report.
+        #[arg(short, long, default_value_t = db::DEFAULT_REPORT_DAYS)]
+        days: u16,
+    },
     /// Starts the web server.
     Serve,
+    /// Shows how the screen time evolves for the events in a JSON script, without touching the
+    /// database.
+    ///
+    /// The script is a list of events like
+    /// `{"type": "time_entry", "created": "2023-01-01T12:00:00", "time": 60}` or
+    /// `{"type": "adjustment", "created": "2023-01-01T13:00:00", "adjustment": 10,
+    /// "expires_in_days": 7}`.
+    Simulate {
+        /// The path to the script.
+        script: std::path::PathBuf,
+        /// The lowest value the screen time can reach, e.g. -15 to allow a small negative buffer.
+        #[arg(long, default_value_t = 0, allow_negative_numbers = true)]
+        floor: i32,
+    },
+    /// Shows the number of adjustments per adjustment type, and their combined effect.
+    Stats {
+        #[command(flatten)]
+        output: OutputArgs,
+    },
     /// Returns the current screen time.
-    Time,
+    Time {
+        /// The format of the screen time.
+        #[arg(short, long, value_enum, default_value_t)]
+        time_format: TimeFormat,
+
+        /// Prints the SQL queries that are used to calculate the screen time to stderr.
+        #[arg(long)]
+        explain_sql: bool,
+
+        /// Also prints whether an initial balance has been set.
+        #[arg(long)]
+        verbose: bool,
+
+        /// Prints the screen time in minutes as a shell variable assignment, e.g.
+        /// `SCREEN_TIME_MINUTES=90`, to use with `eval`. A different variable name can be given.
+        #[arg(
+            long,
+            value_name = "NAME",
+            num_args = 0..=1,
+            default_missing_value = "SCREEN_TIME_MINUTES",
+            value_parser = parse_variable_name,
+            conflicts_with = "verbose"
+        )]
+        export: Option<String>,
+    },
+    /// Lists the adjustments that had the biggest impact on the screen time.
+    TopAdjustments {
+        /// The maximum number of adjustments to return.
+        #[arg(short, long)]
+        limit: Option<u8>,
+        /// Only returns rewards or penalties.
+        #[arg(short, long, value_enum)]
+        sign: Option<AdjustmentSign>,
+    },
     /// Commands related to time entries.
     TimeEntry {
         #[command(subcommand)]
         command: Option<TimeEntryCommands>,
     },
+    /// Checks the screen time against a recalculation from the adjustments selected by the
+    /// database, e.g. after editing the database by hand. Exits with an error if they disagree.
+    Verify,
+}
+
+/// Options that control how listed data is printed.
+#[derive(clap::Args)]
+struct OutputArgs {
+    /// The output format.
+    #[arg(short, long, value_enum, default_value_t)]
+    format: OutputFormat,
+    /// Prints only the values of the given column, one per line, e.g. `id`.
+    #[arg(long)]
+    field: Option<String>,
+}
+
+impl OutputArgs {
+    /// Returns the writer that prints the data as requested.
+    fn writer(&self) -> Box<dyn OutputWriter> {
+        output::writer(self.format, self.field.as_deref())
+    }
 }
 
 #[derive(Subcommand)]
@@ -193,6 +1115,15 @@ enum AdjustmentCommands {
         /// Return only adjustments created after the given date.
         #[arg(short, long)]
         since: Option<chrono::NaiveDate>,
+        /// Return only adjustments created since the current time entry.
+        #[arg(long)]
+        since_last_entry: bool,
+        /// Shows the creation dates in the given timezone, e.g. Europe/Brussels. Defaults to the
+        /// timezone setting.
+        #[arg(long)]
+        tz: Option<Tz>,
+        #[command(flatten)]
+        output: OutputArgs,
     },
     /// Adds a new adjustment.
     Add {
@@ -203,12 +1134,22 @@ enum AdjustmentCommands {
         /// The comment of the adjustment.
         #[arg(short, long)]
         comment: Option<String>,
+
+        /// The creation date of the adjustment, e.g. 2023-01-01T12:00:00. Defaults to now.
+        #[arg(long)]
+        created: Option<chrono::NaiveDateTime>,
+
+        /// Allows a creation date in the future.
+        #[arg(long)]
+        allow_future: bool,
     },
     /// Deletes the adjustment with the given ID.
     Delete {
         /// The ID of the adjustment to delete.
         id: u64,
     },
+    /// Deletes the most recent adjustment and prints the new screen time.
+    Undo,
 }
 
 #[derive(Subcommand)]
@@ -219,6 +1160,11 @@ enum AdjustmentTypeCommands {
         /// The maximum number of adjustment types to return.
         #[arg(short, long)]
         limit: Option<u8>,
+        /// Only lists the adjustment types without adjustments, which can be deleted.
+        #[arg(long)]
+        deletable: bool,
+        #[command(flatten)]
+        output: OutputArgs,
     },
     /// Adds a new adjustment type.
     Add {
@@ -226,9 +1172,47 @@ enum AdjustmentTypeCommands {
         #[arg(short, long)]
         description: String,
 
-        /// The adjustment value of the adjustment type.
+        /// The adjustment value of the adjustment type, in minutes.
+        #[arg(short, long, allow_negative_numbers = true, value_parser = parse_adjustment)]
+        adjustment: i32,
+
+        /// The position of the adjustment type in listings. Lower values are listed first.
+        #[arg(short, long)]
+        sort_order: Option<i32>,
+
+        /// The number of days after which positive adjustments of this type no longer count.
         #[arg(short, long)]
-        adjustment: i8,
+        expires_in_days: Option<u16>,
+
+        /// The color of the adjustment type, as a hex code like #00ff00.
+        #[arg(short, long, value_parser = parse_color)]
+        color: Option<String>,
+    },
+    /// Updates the adjustment type with the given ID.
+    #[command(group(clap::ArgGroup::new("changes").required(true).multiple(true)))]
+    Update {
+        /// The ID of the adjustment type to update.
+        id: u64,
+
+        /// The new description of the adjustment type.
+        #[arg(short, long, group = "changes")]
+        description: Option<String>,
+
+        /// The new adjustment value of the adjustment type, in minutes.
+        #[arg(short, long, group = "changes", allow_negative_numbers = true, value_parser = parse_adjustment)]
+        adjustment: Option<i32>,
+
+        /// The new position of the adjustment type in listings. Lower values are listed first.
+        #[arg(short, long, group = "changes", allow_negative_numbers = true)]
+        sort_order: Option<i32>,
+
+        /// The new number of days after which positive adjustments of this type no longer count.
+        #[arg(short, long, group = "changes")]
+        expires_in_days: Option<u16>,
+
+        /// The new color of the adjustment type, as a hex code like #00ff00.
+        #[arg(short, long, group = "changes", value_parser = parse_color)]
+        color: Option<String>,
     },
     /// Deletes the adjustment type with the given ID.
     Delete {
@@ -238,22 +1222,73 @@ enum AdjustmentTypeCommands {
     },
 }
 
+#[derive(Subcommand)]
+#[command(arg_required_else_help = true)]
+enum ConfigCommands {
+    /// Lists the settings that are stored in the database.
+    List,
+    /// Returns the value of a setting.
+    Get {
+        /// The name of the setting.
+        name: String,
+    },
+    /// Stores the value of a setting in the database.
+    Set {
+        /// The name of the setting.
+        name: String,
+        /// The value of the setting.
+        value: String,
+    },
+}
+
 #[derive(Subcommand)]
 #[command(arg_required_else_help = true)]
 enum TimeEntryCommands {
     /// Returns the current time entry.
-    Current,
+    Current {
+        /// Prints the full time entry as JSON, including the ID and creation date.
+        #[arg(long)]
+        json: bool,
+    },
     /// Lists the available time entries.
     List {
         /// The maximum number of time entries to return.
         #[arg(short, long)]
         limit: Option<u8>,
+
+        /// Also shows the net adjustment that was made until the next time entry.
+        #[arg(short, long)]
+        context: bool,
+
+        /// Shows the creation dates in the given timezone, e.g. Europe/Brussels. Defaults to the
+        /// timezone setting.
+        #[arg(long)]
+        tz: Option<Tz>,
+
+        #[command(flatten)]
+        output: OutputArgs,
     },
     /// Adds a new time entry.
     Add {
         /// The time of the time entry.
         #[arg(short, long)]
         time: u16,
+
+        /// An optional name for the session that starts with the time entry.
+        #[arg(short, long)]
+        label: Option<String>,
+
+        /// The creation date of the time entry, e.g. 2023-01-01T12:00:00. Defaults to now.
+        #[arg(long)]
+        created: Option<chrono::NaiveDateTime>,
+
+        /// Adds the time entry even if the latest one was added less than the minimum interval ago.
+        #[arg(short, long)]
+        force: bool,
+
+        /// Allows a creation date in the future.
+        #[arg(long)]
+        allow_future: bool,
     },
     /// Deletes the time entry with the given ID.
     Delete {
@@ -1,59 +1,332 @@
+use diesel::deserialize::{FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::mysql::{Mysql, MysqlValue};
 use diesel::prelude::*;
+use diesel::serialize::{IsNull, Output, ToSql};
 use serde::ser::SerializeStruct;
 use serde::{Deserialize, Serialize};
+use std::io::Write;
 use tabled::Tabled;
 
-#[derive(Queryable, Selectable, Serialize, Tabled)]
+#[derive(Deserialize, Insertable, Queryable, Selectable, Serialize, Tabled)]
 #[diesel(table_name = crate::schema::adjustment_type)]
 #[diesel(check_for_backend(diesel::mysql::Mysql))]
 pub struct AdjustmentType {
+    #[serde(with = "crate::id")]
     pub id: u64,
     pub description: String,
-    pub adjustment: i8,
+    pub adjustment: i32,
+    // Added in version 2 of the export format.
+    #[serde(default)]
+    #[tabled(display_with = "display_optional")]
+    pub sort_order: Option<i32>,
+    // The number of days after which positive adjustments of this type no longer count. Added in
+    // version 3 of the export format.
+    #[serde(default)]
+    #[tabled(display_with = "display_optional")]
+    pub expires_in_days: Option<u16>,
+    // A color hint for clients, as a hex code like "#00ff00". Added in version 5 of the export
+    // format.
+    #[serde(default)]
+    #[tabled(display_with = "display_optional_string")]
+    pub color: Option<String>,
 }
 
 #[derive(Deserialize, Insertable)]
 #[diesel(table_name = crate::schema::adjustment_type)]
 pub struct NewAdjustmentType {
     pub description: String,
-    pub adjustment: i8,
+    pub adjustment: i32,
+    pub sort_order: Option<i32>,
+    #[serde(default)]
+    pub expires_in_days: Option<u16>,
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+impl NewAdjustmentType {
+    /// Validates the new adjustment type. Returns all problems that were found.
+    #[must_use]
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        if self.description.trim().is_empty() {
+            errors.push(FieldError::new(
+                "description",
+                "The description can't be empty.",
+            ));
+        }
+        if self.description.chars().count() > MAX_VARCHAR_LENGTH {
+            errors.push(FieldError::new(
+                "description",
+                &format!("The description can't be longer than {MAX_VARCHAR_LENGTH} characters."),
+            ));
+        }
+        if self.adjustment.abs() > MAX_ADJUSTMENT {
+            errors.push(FieldError::new(
+                "adjustment",
+                &format!("The adjustment must be between -{MAX_ADJUSTMENT} and {MAX_ADJUSTMENT} minutes."),
+            ));
+        }
+        if self
+            .color
+            .as_deref()
+            .is_some_and(|color| !is_valid_color(color))
+        {
+            errors.push(FieldError::new(
+                "color",
+                "The color must be a hex code like #00ff00.",
+            ));
+        }
+        errors
+    }
+}
+
+/// Returns whether the given string is a hex color code like "#00ff00".
+#[must_use]
+pub fn is_valid_color(color: &str) -> bool {
+    color
+        .strip_prefix('#')
+        .is_some_and(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// The action that was taken for an adjustment type when upserting.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpsertAction {
+    Created,
+    Updated,
+}
+
+/// An adjustment type that was created or updated by an upsert.
+#[derive(Serialize)]
+pub struct AdjustmentTypeUpsert {
+    pub action: UpsertAction,
+    #[serde(flatten)]
+    pub adjustment_type: AdjustmentType,
 }
 
-#[derive(Associations, Debug, Queryable, Selectable, Serialize, Tabled)]
+/// Holds the changes to apply to an existing adjustment type. Fields that are `None` are left
+/// unchanged.
+#[derive(AsChangeset, Default, Deserialize)]
+#[diesel(table_name = crate::schema::adjustment_type)]
+pub struct AdjustmentTypeChangeset {
+    pub description: Option<String>,
+    pub adjustment: Option<i32>,
+    pub sort_order: Option<i32>,
+    pub expires_in_days: Option<u16>,
+    pub color: Option<String>,
+}
+
+#[derive(
+    Associations, Debug, Deserialize, Insertable, Queryable, Selectable, Serialize, Tabled,
+)]
 #[diesel(table_name = crate::schema::adjustment)]
 #[diesel(check_for_backend(diesel::mysql::Mysql))]
 #[diesel(belongs_to(AdjustmentType))]
 pub struct Adjustment {
+    #[serde(with = "crate::id")]
     pub id: u64,
+    // Serialize as `type` so the output matches the field name used in `NewAdjustment`.
+    #[serde(rename = "type", with = "crate::id")]
     pub adjustment_type_id: u64,
     pub created: chrono::NaiveDateTime,
     #[tabled(display_with = "display_optional_string")]
     pub comment: Option<String>,
+    // Added in version 6 of the export format.
+    #[serde(default)]
+    pub source: Source,
+}
+
+/// Where a time entry or adjustment was created.
+#[derive(
+    AsExpression, Clone, Copy, Debug, Default, Deserialize, FromSqlRow, PartialEq, Serialize,
+)]
+#[diesel(sql_type = diesel::sql_types::Varchar)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    /// The command line. Records that were created before the source was tracked are assumed to
+    /// come from here.
+    #[default]
+    Cli,
+    /// The REST API.
+    Web,
+    /// The application itself, like the corrections that are made to reach a target time.
+    Auto,
+    /// An import of adjustments.
+    Import,
+}
+
+impl Source {
+    /// Returns the value as it is stored in the database.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Source::Cli => "cli",
+            Source::Web => "web",
+            Source::Auto => "auto",
+            Source::Import => "import",
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl ToSql<diesel::sql_types::Varchar, Mysql> for Source {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Mysql>) -> diesel::serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<diesel::sql_types::Varchar, Mysql> for Source {
+    fn from_sql(value: MysqlValue<'_>) -> diesel::deserialize::Result<Self> {
+        match value.as_bytes() {
+            b"cli" => Ok(Source::Cli),
+            b"web" => Ok(Source::Web),
+            b"auto" => Ok(Source::Auto),
+            b"import" => Ok(Source::Import),
+            other => Err(format!("Unknown source {}", String::from_utf8_lossy(other)).into()),
+        }
+    }
+}
+
+/// Whether an adjustment adds or removes time.
+#[derive(Clone, Copy, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum AdjustmentSign {
+    /// Adjustments that add time, i.e. rewards.
+    Positive,
+    /// Adjustments that remove time, i.e. penalties.
+    Negative,
+}
+
+/// An adjustment together with the description and value of its type.
+#[derive(Serialize, Tabled)]
+pub struct TopAdjustment {
+    #[serde(serialize_with = "crate::id::serialize")]
+    pub id: u64,
+    pub description: String,
+    pub adjustment: i32,
+    pub created: chrono::NaiveDateTime,
+    #[tabled(display_with = "display_optional_string")]
+    pub comment: Option<String>,
 }
 
 #[derive(Deserialize, Insertable)]
 #[diesel(table_name = crate::schema::adjustment)]
 pub struct NewAdjustment {
-    #[serde(rename(deserialize = "type"))]
+    #[serde(
+        rename(deserialize = "type"),
+        deserialize_with = "crate::id::deserialize"
+    )]
     pub adjustment_type_id: u64,
     pub comment: Option<String>,
     pub created: Option<chrono::NaiveDateTime>,
+    // This is set by the application, depending on where the adjustment is created.
+    #[serde(skip)]
+    pub source: Source,
+}
+
+impl NewAdjustment {
+    /// Validates the new adjustment. Returns all problems that were found.
+    ///
+    /// This only checks that the comment fits in the database. A lower maximum length can be
+    /// configured, which is applied by `db::insert_adjustment()`.
+    #[must_use]
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        if let Some(comment) = &self.comment {
+            if comment.chars().count() > MAX_VARCHAR_LENGTH {
+                errors.push(FieldError::new(
+                    "comment",
+                    &format!("The comment can't be longer than {MAX_VARCHAR_LENGTH} characters."),
+                ));
+            }
+        }
+        errors
+    }
+}
+
+/// What happens with comments that are longer than the maximum length.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CommentOverflowPolicy {
+    /// The adjustment is rejected.
+    #[default]
+    Reject,
+    /// The comment is shortened, ending with an ellipsis.
+    Truncate,
+}
+
+impl std::str::FromStr for CommentOverflowPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "reject" => Ok(Self::Reject),
+            "truncate" => Ok(Self::Truncate),
+            _ => Err(format!(
+                "Invalid comment overflow policy {value}, use reject or truncate"
+            )),
+        }
+    }
+}
+
+/// A reference to an adjustment type, either by ID or by description.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum AdjustmentTypeRef {
+    Id(u64),
+    Description(String),
+}
+
+impl fmt::Display for AdjustmentTypeRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AdjustmentTypeRef::Id(id) => write!(f, "with ID {id}"),
+            AdjustmentTypeRef::Description(description) => write!(f, "\"{description}\""),
+        }
+    }
+}
+
+/// An adjustment to import. This has the same shape as a `NewAdjustment` in the API, but the
+/// adjustment type can also be referenced by its description.
+#[derive(Deserialize)]
+pub struct ImportedAdjustment {
+    #[serde(rename = "type")]
+    pub adjustment_type: AdjustmentTypeRef,
+    pub comment: Option<String>,
+    pub created: Option<chrono::NaiveDateTime>,
 }
 
 /// Represents a time entry in the database.
 ///
-/// It has three public fields:
+/// It has four public fields:
 /// - `id` of type `u64`, which is the unique identifier of the time entry.
 /// - `time` of type `u16`, which represents the total number of minutes.
 /// - `created` of type `chrono::NaiveDateTime`, which is the timestamp when the time entry was created.
-#[derive(Debug, Queryable, Selectable, Tabled)]
+/// - `label` of type `Option<String>`, which is an optional name for the session that starts with
+///   the time entry, e.g. "Weekend allowance".
+#[derive(Debug, Deserialize, Insertable, Queryable, Selectable, Tabled)]
 #[diesel(table_name = crate::schema::time_entry)]
 #[diesel(check_for_backend(diesel::mysql::Mysql))]
 pub struct TimeEntry {
+    // Serialized by the `Serialize` implementation below.
+    #[serde(deserialize_with = "crate::id::deserialize")]
     pub id: u64,
     #[tabled(display_with = "format_time")]
     pub time: u16,
     pub created: chrono::NaiveDateTime,
+    // Added in version 4 of the export format.
+    #[serde(default)]
+    #[tabled(display_with = "display_optional_string")]
+    pub label: Option<String>,
+    // Added in version 6 of the export format.
+    #[serde(default)]
+    pub source: Source,
 }
 
 /// Formats a number of minutes into a string in the format "hh:mm".
@@ -61,10 +334,54 @@ pub struct TimeEntry {
 /// This function is used to format the `time` field of a `TimeEntry` into a human-readable string.
 /// It is passed by reference to the `display_with` attribute of the `tabled` macro.
 #[allow(clippy::trivially_copy_pass_by_ref)]
-fn format_time(time: &u16) -> String {
+#[must_use]
+pub fn format_time(time: &u16) -> String {
     format!("{:01}:{:02}", time / 60, time % 60)
 }
 
+/// Formats a number of minutes into a human-readable string like "2h 30m", "2h" or "45m".
+#[allow(clippy::trivially_copy_pass_by_ref)]
+#[must_use]
+pub fn format_time_human(time: &u16) -> String {
+    match (time / 60, time % 60) {
+        (0, minutes) => format!("{minutes}m"),
+        (hours, 0) => format!("{hours}h"),
+        (hours, minutes) => format!("{hours}h {minutes}m"),
+    }
+}
+
+/// Formats a number of minutes that can be negative, like "-1h 15m".
+#[allow(clippy::trivially_copy_pass_by_ref)]
+#[must_use]
+pub fn format_signed_time_human(time: &i32) -> String {
+    TimeFormat::Human.format(*time)
+}
+
+/// The format in which a number of minutes is shown.
+#[derive(Clone, Copy, Default, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeFormat {
+    /// Hours and minutes separated by a colon, e.g. "2:30".
+    #[default]
+    Clock,
+    /// Hours and minutes with units, e.g. "2h 30m".
+    Human,
+}
+
+impl TimeFormat {
+    /// Formats the given number of minutes. A negative time is prefixed with a minus sign.
+    #[must_use]
+    pub fn format(self, time: i32) -> String {
+        let sign = if time < 0 { "-" } else { "" };
+        let minutes = u16::try_from(time.unsigned_abs()).unwrap_or(u16::MAX);
+        let formatted = match self {
+            TimeFormat::Clock => format_time(&minutes),
+            TimeFormat::Human => format_time_human(&minutes),
+        };
+        format!("{sign}{formatted}")
+    }
+}
+
 impl TimeEntry {
     /// Returns the `time` field as a formatted string.
     ///
@@ -81,6 +398,12 @@ impl TimeEntry {
     pub fn get_formatted_time(&self) -> String {
         format_time(&self.time)
     }
+
+    /// Returns the `time` field as a human-readable string, like "2h 30m".
+    #[must_use]
+    pub fn get_human_formatted_time(&self) -> String {
+        format_time_human(&self.time)
+    }
 }
 
 // Provide an additional field with the human-readable time string when serializing a TimeEntry.
@@ -89,10 +412,12 @@ impl Serialize for TimeEntry {
     where
         S: serde::Serializer,
     {
-        let mut state = serializer.serialize_struct("TimeEntry", 3)?;
-        state.serialize_field("id", &self.id)?;
+        let mut state = serializer.serialize_struct("TimeEntry", 6)?;
+        state.serialize_field("id", &crate::id::Id(self.id))?;
         state.serialize_field("time", &self.time)?;
         state.serialize_field("created", &self.created)?;
+        state.serialize_field("label", &self.label)?;
+        state.serialize_field("source", &self.source)?;
         state.serialize_field("time_formatted", &self.get_formatted_time())?;
         state.end()
     }
@@ -102,15 +427,478 @@ use std::fmt;
 
 impl fmt::Display for TimeEntry {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.get_formatted_time())
+        match &self.label {
+            Some(label) => write!(f, "{} ({label})", self.get_formatted_time()),
+            None => write!(f, "{}", self.get_formatted_time()),
+        }
     }
 }
 
+/// A time entry together with the adjustments that were made until the next time entry.
+#[derive(Serialize, Tabled)]
+pub struct TimeEntryWithContext {
+    #[serde(serialize_with = "crate::id::serialize")]
+    pub id: u64,
+    /// The time of the time entry, which is the adjusted time right after it was created.
+    #[tabled(display_with = "format_time")]
+    pub time: u16,
+    pub created: chrono::NaiveDateTime,
+    /// The net number of minutes that were added or removed by adjustments until the next time
+    /// entry.
+    pub net_adjustment: i64,
+}
+
 #[derive(Deserialize, Insertable)]
 #[diesel(table_name = crate::schema::time_entry)]
 pub struct NewTimeEntry {
     pub time: u16,
     pub created: Option<chrono::NaiveDateTime>,
+    #[serde(default)]
+    pub label: Option<String>,
+    // This is set by the application, depending on where the time entry is created.
+    #[serde(skip)]
+    pub source: Source,
+}
+
+/// A setting that is stored in the database.
+#[derive(Insertable, Queryable, Selectable, Serialize, Tabled)]
+#[diesel(table_name = crate::schema::setting)]
+#[diesel(check_for_backend(diesel::mysql::Mysql))]
+pub struct Setting {
+    pub name: String,
+    pub value: String,
+}
+
+/// The version of the export format that is produced by this version of the application.
+///
+/// Version history:
+/// - 1: Initial version.
+/// - 2: Adds the `sort_order` field to adjustment types.
+/// - 3: Adds the `expires_in_days` field to adjustment types.
+/// - 4: Adds the `label` field to time entries.
+/// - 5: Adds the `color` field to adjustment types.
+/// - 6: Adds the `source` field to adjustments and time entries.
+pub const EXPORT_VERSION: u32 = 6;
+
+/// A full export of the database, which can be restored later.
+#[derive(Deserialize, Serialize)]
+pub struct Export {
+    /// The version of the export format. Documents without a version predate versioning and are
+    /// treated as version 1.
+    #[serde(default = "Export::initial_version")]
+    pub version: u32,
+    pub adjustment_types: Vec<AdjustmentType>,
+    pub adjustments: Vec<Adjustment>,
+    pub time_entries: Vec<TimeEntry>,
+}
+
+impl Export {
+    fn initial_version() -> u32 {
+        1
+    }
+
+    /// Checks that the export can be restored by this version of the application.
+    ///
+    /// Exports made by older versions are supported: fields that were added later are filled with
+    /// their default values when the export is deserialized.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the export was made by a newer version of the application.
+    pub fn check_version(&self) -> Result<(), String> {
+        if self.version > EXPORT_VERSION {
+            return Err(format!(
+                "Export version {} is not supported, the newest supported version is {EXPORT_VERSION}",
+                self.version
+            ));
+        }
+        Ok(())
+    }
+
+    /// Compares this export with another one, table by table.
+    ///
+    /// Rows are matched by ID. The version of the exports is not compared, so an export can be
+    /// compared with a restored copy that was exported by a newer version.
+    #[must_use]
+    pub fn diff(&self, other: &Export) -> Vec<TableDiff> {
+        vec![
+            TableDiff::new(
+                "adjustment_types",
+                &self.adjustment_types,
+                &other.adjustment_types,
+                |r| r.id,
+            ),
+            TableDiff::new("adjustments", &self.adjustments, &other.adjustments, |r| {
+                r.id
+            }),
+            TableDiff::new(
+                "time_entries",
+                &self.time_entries,
+                &other.time_entries,
+                |r| r.id,
+            ),
+        ]
+    }
+}
+
+/// The differences between the rows of a table in two exports, by ID.
+#[derive(Debug, Default, PartialEq)]
+pub struct TableDiff {
+    pub table: &'static str,
+    /// The rows that only exist in the second export.
+    pub added: Vec<u64>,
+    /// The rows that only exist in the first export.
+    pub removed: Vec<u64>,
+    /// The rows that exist in both exports, but with different values.
+    pub changed: Vec<u64>,
+}
+
+impl TableDiff {
+    fn new<T: Serialize>(table: &'static str, a: &[T], b: &[T], id: fn(&T) -> u64) -> Self {
+        // Compare the serialized rows, so all fields are taken into account.
+        let rows = |rows: &[T]| -> std::collections::BTreeMap<u64, serde_json::Value> {
+            rows.iter()
+                .map(|row| (id(row), serde_json::to_value(row).unwrap()))
+                .collect()
+        };
+        let (a, b) = (rows(a), rows(b));
+        TableDiff {
+            table,
+            added: b.keys().filter(|id| !a.contains_key(id)).copied().collect(),
+            removed: a.keys().filter(|id| !b.contains_key(id)).copied().collect(),
+            changed: a
+                .iter()
+                .filter(|(id, row)| b.get(id).is_some_and(|other| other != *row))
+                .map(|(id, _)| *id)
+                .collect(),
+        }
+    }
+
+    /// Returns whether the table is the same in both exports.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// The adjusted time at a given moment.
+#[derive(Debug, Serialize)]
+pub struct BalancePoint {
+    pub timestamp: chrono::NaiveDateTime,
+    pub time: i32,
+}
+
+/// The current adjusted time.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Balance {
+    /// The adjusted time in minutes. This is negative if the balance floor allows it.
+    pub time: i32,
+    /// Whether a time entry or adjustment has ever been recorded. If not, the time of 0 does not
+    /// mean the screen time has been used up, but that no initial balance has been set yet.
+    pub initialized: bool,
+}
+
+/// The adjusted time, checked against an independent calculation by the database.
+#[derive(Debug, PartialEq)]
+pub struct BalanceCheck {
+    /// The adjusted time, as calculated by applying the adjustments one by one.
+    pub fold: i32,
+    /// The adjusted time, as recalculated from the adjustments that the database selected.
+    pub recalculated: i32,
+    /// The time of the current time entry plus the sum of the adjustments since, without the
+    /// balance floor.
+    pub aggregate: i32,
+}
+
+impl BalanceCheck {
+    /// Returns whether both calculations of the adjusted time agree.
+    #[must_use]
+    pub fn is_consistent(&self) -> bool {
+        self.fold == self.recalculated
+    }
+
+    /// Returns the number of minutes by which the balance floor raised the adjusted time above the
+    /// plain sum of the adjustments.
+    #[must_use]
+    pub fn floor_correction(&self) -> i64 {
+        i64::from(self.recalculated) - i64::from(self.aggregate)
+    }
+}
+
+/// An adjustment that counts towards the adjusted time.
+#[derive(Debug, Serialize)]
+pub struct ContributingAdjustment {
+    #[serde(flatten)]
+    pub adjustment: Adjustment,
+    /// The value of the adjustment type, in minutes.
+    pub value: i32,
+    /// The adjusted time after this adjustment. Expired rewards don't change it, and it can't go
+    /// below the balance floor.
+    pub running_total: i32,
+}
+
+/// A record that was created or deleted, as reported by the changes feed.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Change {
+    Adjustment {
+        #[serde(serialize_with = "crate::id::serialize")]
+        id: u64,
+        created: chrono::NaiveDateTime,
+        #[serde(serialize_with = "crate::id::serialize")]
+        adjustment_type_id: u64,
+        comment: Option<String>,
+    },
+    TimeEntry {
+        #[serde(serialize_with = "crate::id::serialize")]
+        id: u64,
+        created: chrono::NaiveDateTime,
+        time: u16,
+        label: Option<String>,
+    },
+    Deleted {
+        record_type: FeedItemType,
+        #[serde(serialize_with = "crate::id::serialize")]
+        id: u64,
+    },
+}
+
+/// The changes made after a given version, in the order they were made.
+#[derive(Debug, Serialize)]
+pub struct ChangeFeed {
+    pub changes: Vec<Change>,
+    /// The value to pass as `after` to retrieve the next changes. This is `None` if there are no
+    /// changes at all.
+    #[serde(serialize_with = "crate::id::serialize_option")]
+    pub next_after: Option<u64>,
+}
+
+/// The kind of record shown in the activity feed.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedItemType {
+    Adjustment,
+    TimeEntry,
+}
+
+impl FeedItemType {
+    /// All kinds of records.
+    pub const ALL: [FeedItemType; 2] = [FeedItemType::Adjustment, FeedItemType::TimeEntry];
+
+    /// Returns the name of the kind of record, as used in the API and the change log.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FeedItemType::Adjustment => "adjustment",
+            FeedItemType::TimeEntry => "time_entry",
+        }
+    }
+}
+
+impl std::str::FromStr for FeedItemType {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "adjustment" => Ok(Self::Adjustment),
+            "time_entry" => Ok(Self::TimeEntry),
+            _ => Err(format!(
+                "Invalid type {value}, use adjustment or time_entry"
+            )),
+        }
+    }
+}
+
+/// An adjustment or time entry in the activity feed, ready to be shown to the user.
+#[derive(Debug, Serialize)]
+pub struct FeedItem {
+    #[serde(rename = "type")]
+    pub item_type: FeedItemType,
+    #[serde(serialize_with = "crate::id::serialize")]
+    pub id: u64,
+    pub created: chrono::NaiveDateTime,
+    /// A description of the event, like "+2 Cleaned room" or "Reset to 2:00".
+    pub label: String,
+    /// The adjusted time right after the event, in minutes.
+    pub balance_after: i32,
+}
+
+/// An adjustment together with the adjusted time right after it was applied.
+#[derive(Debug, Serialize)]
+pub struct AdjustmentWithImpact {
+    #[serde(flatten)]
+    pub adjustment: Adjustment,
+    /// The adjusted time right after the adjustment, in minutes. This doesn't go below the balance
+    /// floor, so a penalty can have less effect than its value.
+    pub balance_after: i32,
+}
+
+/// A page of adjustments, retrieved with an ID cursor.
+#[derive(Debug, Serialize)]
+pub struct AdjustmentPage {
+    /// The adjustments, most recently recorded first.
+    pub adjustments: Vec<Adjustment>,
+    /// The value to pass as `before_id` to retrieve the next, older page. This is `None` if there
+    /// are no older adjustments.
+    #[serde(serialize_with = "crate::id::serialize_option")]
+    pub next_before_id: Option<u64>,
+    /// The value to pass as `after_id` to retrieve the adjustments that are recorded later.
+    #[serde(serialize_with = "crate::id::serialize_option")]
+    pub next_after_id: Option<u64>,
+}
+
+/// An overview of the current state, with all values taken at the same moment.
+#[derive(Serialize)]
+pub struct DashboardSnapshot {
+    /// The adjusted time in minutes.
+    pub time: i32,
+    pub latest_time_entry: Option<TimeEntry>,
+    pub latest_adjustment: Option<Adjustment>,
+    pub adjustment_type_count: i64,
+    pub adjustment_count: i64,
+    pub time_entry_count: i64,
+}
+
+/// The adjustments that were, or would be, deleted by pruning.
+#[derive(Debug, Default, PartialEq)]
+pub struct PruneSummary {
+    /// The number of adjustments.
+    pub count: i64,
+    /// The lowest ID of the adjustments.
+    pub first_id: Option<u64>,
+    /// The highest ID of the adjustments.
+    pub last_id: Option<u64>,
+}
+
+/// A summary of the adjustments made over a number of days.
+#[derive(Serialize)]
+pub struct Report {
+    /// The number of days covered by the report.
+    pub days: u16,
+    /// The total number of adjustments made.
+    pub total_adjustments: usize,
+    /// The net number of minutes that were added or removed by the adjustments.
+    pub net_minutes: i64,
+    /// The day on which the most adjustments were made.
+    pub busiest_day: Option<chrono::NaiveDate>,
+    /// The adjustment type that was used the most.
+    pub most_used_adjustment_type: Option<AdjustmentType>,
+    /// The number of adjustments, of all time, that can't affect any adjusted time since they
+    /// were made before the earliest time entry. These can be pruned.
+    pub unreachable_adjustments: usize,
+}
+
+/// The number of adjustments made for an adjustment type, and their combined effect.
+#[derive(Debug, PartialEq, Serialize, Tabled)]
+pub struct AdjustmentStat {
+    /// The description of the adjustment type.
+    pub description: String,
+    /// The number of adjustments of this type.
+    pub count: i64,
+    /// The number of minutes that were added or removed by these adjustments.
+    pub total_minutes: i64,
+}
+
+/// An adjustment type, together with how often it is used and the combined effect.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct AdjustmentTypeUsage {
+    #[serde(serialize_with = "crate::id::serialize")]
+    pub id: u64,
+    pub description: String,
+    pub adjustment: i32,
+    /// The number of adjustments of this type.
+    pub count: i64,
+    /// The number of minutes that were added or removed by these adjustments.
+    pub net_minutes: i64,
+}
+
+/// An adjustment type as an option of a select input.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct AdjustmentTypeOption {
+    /// The ID of the adjustment type.
+    #[serde(serialize_with = "crate::id::serialize")]
+    pub value: u64,
+    /// The description, followed by the adjustment with its sign, e.g. `Cleaned room (+2)`.
+    pub label: String,
+}
+
+impl From<&AdjustmentType> for AdjustmentTypeOption {
+    fn from(adjustment_type: &AdjustmentType) -> Self {
+        AdjustmentTypeOption {
+            value: adjustment_type.id,
+            label: format!(
+                "{} ({:+})",
+                adjustment_type.description, adjustment_type.adjustment
+            ),
+        }
+    }
+}
+
+impl NewTimeEntry {
+    /// The maximum time of a time entry, in minutes.
+    pub const MAX_TIME: u16 = 24 * 60;
+
+    /// Validates the new time entry. Returns all problems that were found.
+    #[must_use]
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        if self.time > Self::MAX_TIME {
+            errors.push(FieldError::new("time", "The time can't exceed 24 hours."));
+        }
+        if let Some(label) = &self.label {
+            if label.chars().count() > MAX_VARCHAR_LENGTH {
+                errors.push(FieldError::new(
+                    "label",
+                    &format!("The label can't be longer than {MAX_VARCHAR_LENGTH} characters."),
+                ));
+            }
+        }
+        errors
+    }
+}
+
+/// The maximum number of characters that fit in a VARCHAR column.
+pub const MAX_VARCHAR_LENGTH: usize = 255;
+
+/// The largest number of minutes an adjustment type can add or remove: a full day.
+pub const MAX_ADJUSTMENT: i32 = 24 * 60;
+
+/// Whether a record could be deleted in a batch delete.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DeleteStatus {
+    Deleted,
+    NotFound,
+    /// The time entry is the current time entry, which the adjusted time is calculated from. It is
+    /// not deleted.
+    Current,
+}
+
+/// The result of deleting one of the records of a batch delete.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct BatchDeleteResult {
+    #[serde(serialize_with = "crate::id::serialize")]
+    pub id: u64,
+    pub status: DeleteStatus,
+}
+
+/// Describes a problem with one of the fields of a request payload.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct FieldError {
+    /// The name of the field that has a problem.
+    pub field: String,
+    /// A human-readable description of the problem.
+    pub message: String,
+}
+
+impl FieldError {
+    #[must_use]
+    pub fn new(field: &str, message: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.to_string(),
+        }
+    }
 }
 
 fn display_optional_string(o: &Option<String>) -> String {
@@ -120,6 +908,14 @@ fn display_optional_string(o: &Option<String>) -> String {
     }
 }
 
+#[allow(clippy::ref_option)]
+fn display_optional<T: fmt::Display>(o: &Option<T>) -> String {
+    match o {
+        Some(value) => value.to_string(),
+        None => String::new(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -130,6 +926,8 @@ mod tests {
             id: 1,
             time: 120,
             created: chrono::NaiveDateTime::from_timestamp_opt(0, 0).unwrap(),
+            label: None,
+            source: Source::Cli,
         };
         assert_eq!(entry.get_formatted_time(), "2:00");
     }
@@ -140,6 +938,8 @@ mod tests {
             id: 1,
             time: 90,
             created: chrono::NaiveDateTime::from_timestamp_opt(0, 0).unwrap(),
+            label: None,
+            source: Source::Cli,
         };
         assert_eq!(entry.get_formatted_time(), "1:30");
     }
@@ -150,6 +950,8 @@ mod tests {
             id: 1,
             time: 0,
             created: chrono::NaiveDateTime::from_timestamp_opt(0, 0).unwrap(),
+            label: None,
+            source: Source::Cli,
         };
         assert_eq!(entry.get_formatted_time(), "0:00");
     }
@@ -160,6 +962,8 @@ mod tests {
             id: 1,
             time: 9,
             created: chrono::NaiveDateTime::from_timestamp_opt(0, 0).unwrap(),
+            label: None,
+            source: Source::Cli,
         };
         assert_eq!(entry.get_formatted_time(), "0:09");
     }
@@ -170,7 +974,379 @@ mod tests {
             id: 1,
             time: 65,
             created: chrono::NaiveDateTime::from_timestamp_opt(0, 0).unwrap(),
+            label: None,
+            source: Source::Cli,
         };
         assert_eq!(entry.get_formatted_time(), "1:05");
     }
+
+    #[test]
+    fn get_human_formatted_time_returns_correct_format_for_full_hours() {
+        let entry = TimeEntry {
+            id: 1,
+            time: 120,
+            created: chrono::NaiveDateTime::from_timestamp_opt(0, 0).unwrap(),
+            label: None,
+            source: Source::Cli,
+        };
+        assert_eq!(entry.get_human_formatted_time(), "2h");
+    }
+
+    #[test]
+    fn get_human_formatted_time_returns_correct_format_for_partial_hours() {
+        let entry = TimeEntry {
+            id: 1,
+            time: 90,
+            created: chrono::NaiveDateTime::from_timestamp_opt(0, 0).unwrap(),
+            label: None,
+            source: Source::Cli,
+        };
+        assert_eq!(entry.get_human_formatted_time(), "1h 30m");
+    }
+
+    #[test]
+    fn get_human_formatted_time_returns_correct_format_for_zero_minutes() {
+        let entry = TimeEntry {
+            id: 1,
+            time: 0,
+            created: chrono::NaiveDateTime::from_timestamp_opt(0, 0).unwrap(),
+            label: None,
+            source: Source::Cli,
+        };
+        assert_eq!(entry.get_human_formatted_time(), "0m");
+    }
+
+    #[test]
+    fn get_human_formatted_time_returns_correct_format_for_single_digit_minutes() {
+        let entry = TimeEntry {
+            id: 1,
+            time: 9,
+            created: chrono::NaiveDateTime::from_timestamp_opt(0, 0).unwrap(),
+            label: None,
+            source: Source::Cli,
+        };
+        assert_eq!(entry.get_human_formatted_time(), "9m");
+    }
+
+    #[test]
+    fn get_human_formatted_time_returns_correct_format_for_single_digit_hours() {
+        let entry = TimeEntry {
+            id: 1,
+            time: 65,
+            created: chrono::NaiveDateTime::from_timestamp_opt(0, 0).unwrap(),
+            label: None,
+            source: Source::Cli,
+        };
+        assert_eq!(entry.get_human_formatted_time(), "1h 5m");
+    }
+
+    #[test]
+    fn validate_new_adjustment_type_collects_all_errors() {
+        let valid = NewAdjustmentType {
+            description: "Cleaned room".to_string(),
+            adjustment: 2,
+            sort_order: None,
+            expires_in_days: None,
+            color: None,
+        };
+        assert!(valid.validate().is_empty());
+
+        let empty = NewAdjustmentType {
+            description: "  ".to_string(),
+            adjustment: 2,
+            sort_order: None,
+            expires_in_days: None,
+            color: None,
+        };
+        let errors = empty.validate();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "description");
+
+        let too_long = NewAdjustmentType {
+            description: "a".repeat(256),
+            adjustment: 2,
+            sort_order: None,
+            expires_in_days: None,
+            color: None,
+        };
+        assert_eq!(too_long.validate().len(), 1);
+
+        let too_large = NewAdjustmentType {
+            description: "Cleaned the whole house".to_string(),
+            adjustment: 3 * 24 * 60,
+            sort_order: None,
+            expires_in_days: None,
+            color: None,
+        };
+        let errors = too_large.validate();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "adjustment");
+
+        let invalid_color = NewAdjustmentType {
+            description: "Cleaned room".to_string(),
+            adjustment: 2,
+            sort_order: None,
+            expires_in_days: None,
+            color: Some("green".to_string()),
+        };
+        let errors = invalid_color.validate();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "color");
+    }
+
+    #[test]
+    fn is_valid_color_requires_six_digit_hex_code() {
+        assert!(is_valid_color("#00ff00"));
+        assert!(is_valid_color("#A0B1C2"));
+        assert!(!is_valid_color("00ff00"));
+        assert!(!is_valid_color("#0f0"));
+        assert!(!is_valid_color("#00ff0g"));
+        assert!(!is_valid_color("#00ff000"));
+    }
+
+    #[test]
+    fn validate_new_adjustment_checks_comment_length() {
+        let mut adjustment = NewAdjustment {
+            adjustment_type_id: 1,
+            comment: Some("a".repeat(255)),
+            created: None,
+            source: Source::Cli,
+        };
+        assert!(adjustment.validate().is_empty());
+
+        adjustment.comment = Some("a".repeat(256));
+        assert_eq!(
+            adjustment.validate(),
+            vec![FieldError::new(
+                "comment",
+                "The comment can't be longer than 255 characters."
+            )]
+        );
+    }
+
+    #[test]
+    fn parse_comment_overflow_policy() {
+        assert_eq!(
+            "reject".parse::<CommentOverflowPolicy>(),
+            Ok(CommentOverflowPolicy::Reject)
+        );
+        assert_eq!(
+            "truncate".parse::<CommentOverflowPolicy>(),
+            Ok(CommentOverflowPolicy::Truncate)
+        );
+        assert!("ignore".parse::<CommentOverflowPolicy>().is_err());
+    }
+
+    #[test]
+    fn validate_new_time_entry_checks_time() {
+        let mut time_entry = NewTimeEntry {
+            time: NewTimeEntry::MAX_TIME,
+            created: None,
+            label: None,
+            source: Source::Cli,
+        };
+        assert!(time_entry.validate().is_empty());
+
+        time_entry.time += 1;
+        assert_eq!(time_entry.validate()[0].field, "time");
+    }
+
+    #[test]
+    fn validate_new_time_entry_checks_label_length() {
+        let mut time_entry = NewTimeEntry {
+            time: 60,
+            created: None,
+            label: Some("a".repeat(255)),
+            source: Source::Cli,
+        };
+        assert!(time_entry.validate().is_empty());
+
+        time_entry.label = Some("a".repeat(256));
+        assert_eq!(time_entry.validate()[0].field, "label");
+    }
+
+    #[test]
+    fn time_entry_display_includes_label() {
+        let mut entry = TimeEntry {
+            id: 1,
+            time: 90,
+            created: chrono::NaiveDateTime::from_timestamp_opt(0, 0).unwrap(),
+            label: None,
+            source: Source::Cli,
+        };
+        assert_eq!(entry.to_string(), "1:30");
+
+        entry.label = Some("Weekend allowance".to_string());
+        assert_eq!(entry.to_string(), "1:30 (Weekend allowance)");
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(json["label"], "Weekend allowance");
+    }
+
+    #[test]
+    fn export_check_version_rejects_newer_versions() {
+        let mut export = Export {
+            version: EXPORT_VERSION,
+            adjustment_types: vec![],
+            adjustments: vec![],
+            time_entries: vec![],
+        };
+        assert!(export.check_version().is_ok());
+
+        export.version = 1;
+        assert!(export.check_version().is_ok());
+
+        export.version = EXPORT_VERSION + 1;
+        assert!(export.check_version().is_err());
+    }
+
+    #[test]
+    fn export_diff_lists_changed_rows_per_table() {
+        let a: Export = serde_json::from_str(
+            r#"{"version": 4, "adjustment_types": [], "adjustments": [], "time_entries": [
+                {"id": 1, "time": 60, "created": "2023-01-01T12:00:00"},
+                {"id": 2, "time": 30, "created": "2023-01-02T12:00:00"},
+                {"id": 3, "time": 90, "created": "2023-01-03T12:00:00"}
+            ]}"#,
+        )
+        .unwrap();
+        let b: Export = serde_json::from_str(
+            r#"{"version": 5, "adjustment_types": [], "adjustments": [], "time_entries": [
+                {"id": 1, "time": 60, "created": "2023-01-01T12:00:00"},
+                {"id": 3, "time": 90, "created": "2023-01-03T12:00:00", "label": "Weekend"},
+                {"id": 4, "time": 45, "created": "2023-01-04T12:00:00"}
+            ]}"#,
+        )
+        .unwrap();
+
+        // The version is not compared.
+        assert!(a.diff(&a).iter().all(TableDiff::is_empty));
+        let diffs = b.diff(&b);
+        assert_eq!(diffs.len(), 3);
+        assert!(diffs.iter().all(TableDiff::is_empty));
+
+        let diffs = a.diff(&b);
+        assert!(diffs[0].is_empty());
+        assert!(diffs[1].is_empty());
+        assert_eq!(
+            diffs[2],
+            TableDiff {
+                table: "time_entries",
+                added: vec![4],
+                removed: vec![2],
+                changed: vec![3],
+            }
+        );
+    }
+
+    #[test]
+    fn export_without_version_is_treated_as_version_1() {
+        let export: Export = serde_json::from_str(
+            r#"{"adjustment_types": [], "adjustments": [], "time_entries": []}"#,
+        )
+        .unwrap();
+        assert_eq!(export.version, 1);
+    }
+
+    #[test]
+    fn adjustment_type_id_uses_same_field_name_for_input_and_output() {
+        // The adjustment type ID is passed as `type` when creating an adjustment.
+        let new_adjustment: NewAdjustment =
+            serde_json::from_str(r#"{"type": 3, "comment": "Test"}"#).unwrap();
+        assert_eq!(new_adjustment.adjustment_type_id, 3);
+
+        // It should be returned under the same field name when reading the adjustment back.
+        let adjustment = Adjustment {
+            id: 1,
+            adjustment_type_id: new_adjustment.adjustment_type_id,
+            created: chrono::NaiveDateTime::from_timestamp_opt(0, 0).unwrap(),
+            comment: new_adjustment.comment,
+            source: Source::Cli,
+        };
+        let value = serde_json::to_value(&adjustment).unwrap();
+        assert_eq!(value["type"], 3);
+        assert!(value.get("adjustment_type_id").is_none());
+    }
+
+    #[test]
+    fn imported_adjustment_references_type_by_id_or_description() {
+        let adjustments: Vec<ImportedAdjustment> =
+            serde_json::from_str(r#"[{"type": 3}, {"type": "Cleaned room", "comment": "Test"}]"#)
+                .unwrap();
+        assert_eq!(adjustments[0].adjustment_type, AdjustmentTypeRef::Id(3));
+        assert_eq!(
+            adjustments[1].adjustment_type,
+            AdjustmentTypeRef::Description("Cleaned room".to_string())
+        );
+        assert_eq!(adjustments[1].comment, Some("Test".to_string()));
+    }
+
+    #[test]
+    fn time_format_formats_negative_times() {
+        assert_eq!(TimeFormat::Clock.format(90), "1:30");
+        assert_eq!(TimeFormat::Clock.format(-15), "-0:15");
+        assert_eq!(TimeFormat::Human.format(-75), "-1h 15m");
+    }
+
+    #[test]
+    fn adjustment_type_option_label_has_sign() {
+        let adjustment_type = |adjustment| AdjustmentType {
+            id: 1,
+            description: "Cleaned room".to_string(),
+            adjustment,
+            sort_order: None,
+            expires_in_days: None,
+            color: None,
+        };
+        assert_eq!(
+            AdjustmentTypeOption::from(&adjustment_type(2)),
+            AdjustmentTypeOption {
+                value: 1,
+                label: "Cleaned room (+2)".to_string(),
+            }
+        );
+        assert_eq!(
+            AdjustmentTypeOption::from(&adjustment_type(-30)).label,
+            "Cleaned room (-30)"
+        );
+    }
+
+    #[test]
+    fn change_is_tagged_with_its_type() {
+        let change = Change::TimeEntry {
+            id: 1,
+            created: chrono::NaiveDateTime::from_timestamp_opt(0, 0).unwrap(),
+            time: 90,
+            label: None,
+        };
+        let value = serde_json::to_value(&change).unwrap();
+        assert_eq!(value["type"], "time_entry");
+        assert_eq!(value["id"], 1);
+        assert_eq!(value["created"], "1970-01-01T00:00:00");
+        assert_eq!(value["time"], 90);
+
+        let change = Change::Deleted {
+            record_type: FeedItemType::Adjustment,
+            id: 2,
+        };
+        let value = serde_json::to_value(&change).unwrap();
+        assert_eq!(value["type"], "deleted");
+        assert_eq!(value["record_type"], "adjustment");
+        assert_eq!(value["id"], 2);
+    }
+
+    #[test]
+    fn balance_check_allows_the_floor() {
+        let check = |fold, recalculated, aggregate| BalanceCheck {
+            fold,
+            recalculated,
+            aggregate,
+        };
+        assert!(check(45, 45, 45).is_consistent());
+        assert!(!check(45, 30, 30).is_consistent());
+        // Reaching the floor raises the screen time above the sum, which is reported separately.
+        assert!(check(0, 0, -25).is_consistent());
+        assert_eq!(check(0, 0, -25).floor_correction(), 25);
+        assert!(!check(10, 0, -25).is_consistent());
+        assert_eq!(check(45, 45, 45).floor_correction(), 0);
+    }
 }
@@ -0,0 +1,230 @@
+use serde::Serialize;
+use std::fmt::Write;
+use tabled::settings::Style;
+use tabled::Tabled;
+
+/// The format in which data is printed.
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// A human readable table.
+    #[default]
+    Table,
+    /// JSON, for processing by other tools.
+    Json,
+    /// Comma separated values, with a header row.
+    Csv,
+    /// Newline delimited JSON, with one row per line.
+    Ndjson,
+}
+
+/// Rows of data, prepared so they can be written in any format.
+pub struct Rows {
+    headers: Vec<String>,
+    // The values of each row, as they are shown in a table.
+    fields: Vec<Vec<String>>,
+    // Each row serialized as JSON.
+    json: Vec<String>,
+}
+
+impl Rows {
+    #[must_use]
+    pub fn new<T: Serialize + Tabled>(rows: &[T]) -> Self {
+        Rows {
+            headers: T::headers().into_iter().map(String::from).collect(),
+            fields: rows
+                .iter()
+                .map(|row| row.fields().into_iter().map(String::from).collect())
+                .collect(),
+            json: rows
+                .iter()
+                .map(|row| serde_json::to_string(row).unwrap())
+                .collect(),
+        }
+    }
+}
+
+/// Renders rows of data for the command line.
+pub trait OutputWriter {
+    /// Returns the rows as they should be printed, including the final newline.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the rows can't be rendered with the chosen options.
+    fn render(&self, rows: &Rows) -> Result<String, String>;
+
+    /// Prints the rows. Exits with an error if they can't be rendered.
+    fn write(&self, rows: &Rows) {
+        match self.render(rows) {
+            Ok(output) => print!("{output}"),
+            Err(e) => crate::exit_with_error(&e),
+        }
+    }
+}
+
+/// Returns the writer for the given format. If a field is given, only the values of that column
+/// are printed, one per line, regardless of the format.
+#[must_use]
+pub fn writer(format: OutputFormat, field: Option<&str>) -> Box<dyn OutputWriter> {
+    if let Some(field) = field {
+        return Box::new(FieldWriter(field.to_string()));
+    }
+    match format {
+        OutputFormat::Table => Box::new(TableWriter),
+        OutputFormat::Json => Box::new(JsonWriter),
+        OutputFormat::Csv => Box::new(CsvWriter),
+        OutputFormat::Ndjson => Box::new(NdjsonWriter),
+    }
+}
+
+/// Writes the rows as a table.
+pub struct TableWriter;
+
+impl OutputWriter for TableWriter {
+    fn render(&self, rows: &Rows) -> Result<String, String> {
+        let mut builder = tabled::builder::Builder::default();
+        builder.push_record(rows.headers.clone());
+        for fields in &rows.fields {
+            builder.push_record(fields.clone());
+        }
+        let mut table = builder.build();
+        table.with(Style::sharp());
+        Ok(format!("{table}\n"))
+    }
+}
+
+/// Writes the rows as a JSON array.
+pub struct JsonWriter;
+
+impl OutputWriter for JsonWriter {
+    fn render(&self, rows: &Rows) -> Result<String, String> {
+        Ok(format!("[{}]\n", rows.json.join(",")))
+    }
+}
+
+/// Writes the rows as CSV.
+pub struct CsvWriter;
+
+impl OutputWriter for CsvWriter {
+    fn render(&self, rows: &Rows) -> Result<String, String> {
+        let mut output = String::new();
+        for values in std::iter::once(&rows.headers).chain(&rows.fields) {
+            let values: Vec<String> = values.iter().map(|value| csv_value(value)).collect();
+            writeln!(output, "{}", values.join(",")).unwrap();
+        }
+        Ok(output)
+    }
+}
+
+/// Quotes a CSV value if it contains a separator, a quote or a line break.
+fn csv_value(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Writes each row as JSON on a separate line.
+pub struct NdjsonWriter;
+
+impl OutputWriter for NdjsonWriter {
+    fn render(&self, rows: &Rows) -> Result<String, String> {
+        let mut output = String::new();
+        for row in &rows.json {
+            writeln!(output, "{row}").unwrap();
+        }
+        Ok(output)
+    }
+}
+
+/// Writes only the values of a single column, one per line, which is easier to use in scripts.
+pub struct FieldWriter(pub String);
+
+impl OutputWriter for FieldWriter {
+    fn render(&self, rows: &Rows) -> Result<String, String> {
+        let field = &self.0;
+        let Some(index) = rows.headers.iter().position(|header| header == field) else {
+            return Err(format!(
+                "Unknown field {field}, use one of: {}",
+                rows.headers.join(", ")
+            ));
+        };
+        let mut output = String::new();
+        for fields in &rows.fields {
+            writeln!(output, "{}", fields[index]).unwrap();
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Tabled)]
+    struct Row {
+        id: u64,
+        description: String,
+    }
+
+    fn rows() -> Vec<Row> {
+        vec![
+            Row {
+                id: 1,
+                description: "Cleaned room".to_string(),
+            },
+            Row {
+                id: 2,
+                description: "Late, again".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn table_writer_matches_tabled_output() {
+        for rows in [rows(), vec![]] {
+            let mut table = tabled::Table::new(&rows);
+            table.with(Style::sharp());
+            assert_eq!(
+                TableWriter.render(&Rows::new(&rows)).unwrap(),
+                format!("{table}\n")
+            );
+        }
+    }
+
+    #[test]
+    fn json_writers_match_serde_output() {
+        let rows = rows();
+        assert_eq!(
+            JsonWriter.render(&Rows::new(&rows)).unwrap(),
+            format!("{}\n", serde_json::to_string(&rows).unwrap())
+        );
+        assert_eq!(JsonWriter.render(&Rows::new::<Row>(&[])).unwrap(), "[]\n");
+        assert_eq!(
+            NdjsonWriter.render(&Rows::new(&rows)).unwrap(),
+            "{\"id\":1,\"description\":\"Cleaned room\"}\n{\"id\":2,\"description\":\"Late, again\"}\n"
+        );
+    }
+
+    #[test]
+    fn csv_writer_quotes_values() {
+        assert_eq!(
+            CsvWriter.render(&Rows::new(&rows())).unwrap(),
+            "id,description\n1,Cleaned room\n2,\"Late, again\"\n"
+        );
+        assert_eq!(csv_value("Say \"hi\""), "\"Say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn field_writer_prints_a_single_column() {
+        let rows = Rows::new(&rows());
+        assert_eq!(
+            FieldWriter("id".to_string()).render(&rows).unwrap(),
+            "1\n2\n"
+        );
+        assert_eq!(
+            FieldWriter("time".to_string()).render(&rows).unwrap_err(),
+            "Unknown field time, use one of: id, description"
+        );
+    }
+}
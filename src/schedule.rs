@@ -0,0 +1,211 @@
+use chrono::{Datelike, NaiveDateTime, NaiveTime, TimeZone, Weekday};
+use chrono_tz::Tz;
+
+/// When a background task runs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Schedule {
+    /// At a fixed interval.
+    Every(chrono::Duration),
+    /// Every day at the given time.
+    DailyAt(NaiveTime),
+    /// Every week on the given day, at the given time.
+    WeeklyAt(Weekday, NaiveTime),
+}
+
+impl std::str::FromStr for Schedule {
+    type Err = String;
+
+    /// Parses a schedule like `03:00` for a daily run, `sun 18:00` for a weekly run, or `6h` or
+    /// `30m` for an interval.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Ok(time) = NaiveTime::parse_from_str(value, "%H:%M") {
+            return Ok(Self::DailyAt(time));
+        }
+        if let Some((weekday, time)) = value.split_once(' ') {
+            if let (Ok(weekday), Ok(time)) = (
+                weekday.parse(),
+                NaiveTime::parse_from_str(time.trim(), "%H:%M"),
+            ) {
+                return Ok(Self::WeeklyAt(weekday, time));
+            }
+        }
+
+        parse_interval(value).map(Self::Every).ok_or_else(|| {
+            format!("Invalid schedule {value}, use a time like 03:00, a day and time like sun 18:00 or an interval like 6h or 30m")
+        })
+    }
+}
+
+/// The longest interval that can be parsed, in minutes. This is a year.
+const MAX_INTERVAL_MINUTES: i64 = 366 * 24 * 60;
+
+/// Parses a positive interval in hours or minutes, like `6h` or `30m`. Intervals longer than a
+/// year are rejected.
+#[must_use]
+pub fn parse_interval(value: &str) -> Option<chrono::Duration> {
+    let (number, unit) = value.split_at(value.len().saturating_sub(1));
+    let number: i64 = number.parse().ok().filter(|number| *number > 0)?;
+    let minutes = match unit {
+        "h" => number.checked_mul(60)?,
+        "m" => number,
+        _ => return None,
+    };
+    (minutes <= MAX_INTERVAL_MINUTES).then(|| chrono::Duration::minutes(minutes))
+}
+
+impl Schedule {
+    /// Returns the next moment the task should run, in UTC.
+    ///
+    /// Daily and weekly runs are scheduled in the given timezone, or in UTC if there is none. If
+    /// the time does not exist on a day because of a daylight saving time change, the task runs an
+    /// hour later.
+    #[must_use]
+    pub fn next_run(&self, now: NaiveDateTime, tz: Option<Tz>) -> NaiveDateTime {
+        let (weekday, time) = match self {
+            Schedule::Every(interval) => return now + *interval,
+            Schedule::DailyAt(time) => (None, *time),
+            Schedule::WeeklyAt(weekday, time) => (Some(*weekday), *time),
+        };
+
+        let tz = tz.unwrap_or(Tz::UTC);
+        let local_now = tz.from_utc_datetime(&now).naive_local();
+        let mut next = local_now.date().and_time(time);
+        if let Some(weekday) = weekday {
+            let days_ahead =
+                (7 + weekday.num_days_from_monday() - next.weekday().num_days_from_monday()) % 7;
+            next += chrono::Duration::days(i64::from(days_ahead));
+        }
+        if next <= local_now {
+            next += chrono::Duration::days(if weekday.is_some() { 7 } else { 1 });
+        }
+        tz.from_local_datetime(&next)
+            .earliest()
+            .or_else(|| {
+                tz.from_local_datetime(&(next + chrono::Duration::hours(1)))
+                    .earliest()
+            })
+            .map_or(next, |next| next.naive_utc())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(datetime: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(datetime, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn parse_schedule() {
+        assert_eq!(
+            "03:00".parse(),
+            Ok(Schedule::DailyAt(NaiveTime::from_hms_opt(3, 0, 0).unwrap()))
+        );
+        assert_eq!(
+            "6h".parse(),
+            Ok(Schedule::Every(chrono::Duration::hours(6)))
+        );
+        assert_eq!(
+            "30m".parse(),
+            Ok(Schedule::Every(chrono::Duration::minutes(30)))
+        );
+        assert_eq!(
+            "sun 18:00".parse(),
+            Ok(Schedule::WeeklyAt(
+                Weekday::Sun,
+                NaiveTime::from_hms_opt(18, 0, 0).unwrap()
+            ))
+        );
+        assert_eq!(
+            "Monday 09:30".parse(),
+            Ok(Schedule::WeeklyAt(
+                Weekday::Mon,
+                NaiveTime::from_hms_opt(9, 30, 0).unwrap()
+            ))
+        );
+        assert!("someday 18:00".parse::<Schedule>().is_err());
+        assert!("sun 25:00".parse::<Schedule>().is_err());
+        assert!("0h".parse::<Schedule>().is_err());
+        assert!("6d".parse::<Schedule>().is_err());
+        assert!("25:00".parse::<Schedule>().is_err());
+        assert!("".parse::<Schedule>().is_err());
+    }
+
+    #[test]
+    fn parse_intervals() {
+        assert_eq!(parse_interval("15m"), Some(chrono::Duration::minutes(15)));
+        assert_eq!(parse_interval("1h"), Some(chrono::Duration::hours(1)));
+        assert_eq!(parse_interval("-1h"), None);
+        assert_eq!(parse_interval("15"), None);
+        assert_eq!(parse_interval("m"), None);
+        assert_eq!(parse_interval("8784h"), Some(chrono::Duration::days(366)));
+        assert_eq!(parse_interval("8785h"), None);
+        assert_eq!(parse_interval("10000000000000h"), None);
+        assert_eq!(parse_interval("9223372036854775807h"), None);
+        assert_eq!(parse_interval("9223372036854775807m"), None);
+    }
+
+    #[test]
+    fn next_run_of_interval() {
+        let schedule = Schedule::Every(chrono::Duration::hours(6));
+        assert_eq!(
+            schedule.next_run(at("2023-01-01 22:00"), None),
+            at("2023-01-02 04:00")
+        );
+    }
+
+    #[test]
+    fn next_run_of_daily_schedule() {
+        let schedule: Schedule = "03:00".parse().unwrap();
+        assert_eq!(
+            schedule.next_run(at("2023-01-01 02:00"), None),
+            at("2023-01-01 03:00")
+        );
+        assert_eq!(
+            schedule.next_run(at("2023-01-01 03:00"), None),
+            at("2023-01-02 03:00")
+        );
+        // Brussels is an hour ahead of UTC in winter.
+        assert_eq!(
+            schedule.next_run(at("2023-01-01 12:00"), Some(chrono_tz::Europe::Brussels)),
+            at("2023-01-02 02:00")
+        );
+    }
+
+    #[test]
+    fn next_run_of_weekly_schedule() {
+        // 2023-01-01 is a Sunday.
+        let schedule: Schedule = "sun 18:00".parse().unwrap();
+        assert_eq!(
+            schedule.next_run(at("2023-01-01 12:00"), None),
+            at("2023-01-01 18:00")
+        );
+        assert_eq!(
+            schedule.next_run(at("2023-01-01 18:00"), None),
+            at("2023-01-08 18:00")
+        );
+        assert_eq!(
+            schedule.next_run(at("2023-01-04 12:00"), None),
+            at("2023-01-08 18:00")
+        );
+
+        // Crossing from the end of the week into the next one.
+        let schedule: Schedule = "mon 09:00".parse().unwrap();
+        assert_eq!(
+            schedule.next_run(at("2023-01-01 23:00"), None),
+            at("2023-01-02 09:00")
+        );
+        assert_eq!(
+            schedule.next_run(at("2023-01-02 10:00"), None),
+            at("2023-01-09 09:00")
+        );
+
+        // Monday 00:30 in Brussels is still Sunday in UTC.
+        let schedule: Schedule = "mon 00:30".parse().unwrap();
+        assert_eq!(
+            schedule.next_run(at("2023-01-01 12:00"), Some(chrono_tz::Europe::Brussels)),
+            at("2023-01-01 23:30")
+        );
+    }
+}
@@ -7,6 +7,8 @@ diesel::table! {
         created -> Timestamp,
         #[max_length = 255]
         comment -> Nullable<Varchar>,
+        #[max_length = 10]
+        source -> Varchar,
     }
 }
 
@@ -15,7 +17,30 @@ diesel::table! {
         id -> Unsigned<Bigint>,
         #[max_length = 255]
         description -> Varchar,
-        adjustment -> Tinyint,
+        adjustment -> Integer,
+        sort_order -> Nullable<Integer>,
+        expires_in_days -> Nullable<Unsigned<Smallint>>,
+        #[max_length = 7]
+        color -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    change_log (id) {
+        id -> Unsigned<Bigint>,
+        #[max_length = 20]
+        record_type -> Varchar,
+        record_id -> Unsigned<Bigint>,
+        deleted -> Bool,
+    }
+}
+
+diesel::table! {
+    setting (name) {
+        #[max_length = 255]
+        name -> Varchar,
+        #[max_length = 255]
+        value -> Varchar,
     }
 }
 
@@ -24,7 +49,19 @@ diesel::table! {
         id -> Unsigned<Bigint>,
         time -> Unsigned<Smallint>,
         created -> Timestamp,
+        #[max_length = 255]
+        label -> Nullable<Varchar>,
+        #[max_length = 10]
+        source -> Varchar,
     }
 }
 
-diesel::allow_tables_to_appear_in_same_query!(adjustment, adjustment_type, time_entry,);
+diesel::joinable!(adjustment -> adjustment_type (adjustment_type_id));
+
+diesel::allow_tables_to_appear_in_same_query!(
+    adjustment,
+    adjustment_type,
+    change_log,
+    setting,
+    time_entry,
+);
@@ -0,0 +1,232 @@
+use serde::Serialize;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+/// The number of times an event is sent before giving up.
+const MAX_ATTEMPTS: u32 = 3;
+
+/// The time to wait for the webhook to respond.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The maximum number of bytes that are read from the status line of a response.
+const MAX_STATUS_LINE_LENGTH: u64 = 1024;
+
+/// An event that is sent to the webhook when a resource is created or deleted.
+#[derive(Debug, Serialize)]
+pub struct WebhookEvent {
+    /// What happened, either "created" or "deleted".
+    pub event: &'static str,
+    /// The type of the resource, either "adjustment" or "`time_entry`".
+    pub resource: &'static str,
+    #[serde(serialize_with = "crate::id::serialize")]
+    pub id: u64,
+    /// The adjusted time after the change, in minutes.
+    pub balance_after: i32,
+}
+
+/// A URL to which events are posted.
+///
+/// Only plain `http://` URLs are supported, which is sufficient for home automation systems
+/// running on the local network.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Webhook {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl Webhook {
+    /// Parses a URL like `http://homeassistant.local:8123/api/webhook/screentime`.
+    ///
+    /// The errors don't contain the URL, since its path can contain a secret token.
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or("The webhook URL must start with http://")?;
+        let (authority, path) = rest.find('/').map_or((rest, "/"), |i| rest.split_at(i));
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse()
+                    .map_err(|_| "The webhook URL has an invalid port")?,
+            ),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err("The webhook URL has no host".to_string());
+        }
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+
+    /// Returns the scheme, host and port of the URL. The path is left out because it can contain a
+    /// secret token.
+    #[must_use]
+    pub fn origin(&self) -> String {
+        format!("http://{}:{}", self.host, self.port)
+    }
+
+    /// Returns the value of the `Host` header, which only contains the port if it is not the
+    /// default one.
+    fn host_header(&self) -> String {
+        if self.port == 80 {
+            self.host.clone()
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+
+    /// Sends the event in the background, so the caller is not blocked.
+    ///
+    /// Failed attempts are logged and retried, with an increasing delay between attempts.
+    pub fn send(&self, event: &WebhookEvent) {
+        let webhook = self.clone();
+        let body = serde_json::to_string(event).unwrap();
+        tokio::spawn(async move {
+            for attempt in 1..=MAX_ATTEMPTS {
+                let error = match tokio::time::timeout(TIMEOUT, webhook.post(&body)).await {
+                    Ok(Ok(status)) if (200..300).contains(&status) => return,
+                    Ok(Ok(status)) => format!("status {status}"),
+                    Ok(Err(e)) => e.to_string(),
+                    Err(_) => "timed out".to_string(),
+                };
+                eprintln!("Webhook attempt {attempt} of {MAX_ATTEMPTS} failed: {error}");
+                if attempt < MAX_ATTEMPTS {
+                    tokio::time::sleep(Duration::from_secs(u64::from(attempt))).await;
+                }
+            }
+        });
+    }
+
+    /// Posts the given JSON body to the webhook. Returns the status code of the response.
+    ///
+    /// Only the status line is read, so the webhook doesn't have to close the connection and the
+    /// body of the response can be anything.
+    async fn post(&self, body: &str) -> std::io::Result<u16> {
+        let mut stream = TcpStream::connect((self.host.as_str(), self.port)).await?;
+        let request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            self.path,
+            self.host_header(),
+            body.len()
+        );
+        stream.write_all(request.as_bytes()).await?;
+
+        let mut status_line = Vec::new();
+        BufReader::new(stream)
+            .take(MAX_STATUS_LINE_LENGTH)
+            .read_until(b'\n', &mut status_line)
+            .await?;
+        parse_status(&String::from_utf8_lossy(&status_line)).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid HTTP response")
+        })
+    }
+}
+
+/// Returns the status code from the status line of an HTTP response, e.g. `HTTP/1.1 200 OK`.
+fn parse_status(response: &str) -> Option<u16> {
+    let mut parts = response.lines().next()?.split_whitespace();
+    parts
+        .next()
+        .filter(|version| version.starts_with("HTTP/"))?;
+    parts.next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn parse_webhook_url() {
+        assert_eq!(
+            Webhook::parse("http://homeassistant.local:8123/api/webhook/screentime"),
+            Ok(Webhook {
+                host: "homeassistant.local".to_string(),
+                port: 8123,
+                path: "/api/webhook/screentime".to_string(),
+            })
+        );
+        assert_eq!(
+            Webhook::parse("http://example.com"),
+            Ok(Webhook {
+                host: "example.com".to_string(),
+                port: 80,
+                path: "/".to_string(),
+            })
+        );
+        assert!(Webhook::parse("https://example.com/").is_err());
+        assert!(Webhook::parse("http://example.com:port/").is_err());
+        assert!(Webhook::parse("http:///path").is_err());
+    }
+
+    #[test]
+    fn host_header_contains_non_default_port() {
+        let webhook = Webhook::parse("http://example.com/hook").unwrap();
+        assert_eq!(webhook.host_header(), "example.com");
+        let webhook = Webhook::parse("http://homeassistant.local:8123/hook").unwrap();
+        assert_eq!(webhook.host_header(), "homeassistant.local:8123");
+    }
+
+    #[test]
+    fn parse_status_line() {
+        assert_eq!(parse_status("HTTP/1.1 204 No Content\r\n\r\n"), Some(204));
+        assert_eq!(
+            parse_status("HTTP/1.0 500 Internal Server Error"),
+            Some(500)
+        );
+        assert_eq!(parse_status("SSH-2.0-OpenSSH"), None);
+        assert_eq!(parse_status(""), None);
+    }
+
+    #[tokio::test]
+    async fn post_sends_json_body() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = vec![0; 1024];
+            let length = stream.read(&mut buffer).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&buffer[..length]).to_string()
+        });
+
+        let webhook = Webhook::parse(&format!("http://127.0.0.1:{port}/hook")).unwrap();
+        assert_eq!(webhook.post(r#"{"id":1}"#).await.unwrap(), 204);
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /hook HTTP/1.1\r\n"));
+        assert!(request.contains(&format!("Host: 127.0.0.1:{port}\r\n")));
+        assert!(request.contains("Content-Length: 8\r\n"));
+        assert!(request.ends_with("\r\n\r\n{\"id\":1}"));
+    }
+
+    #[tokio::test]
+    async fn post_reads_only_the_status_line() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = vec![0; 1024];
+            assert!(stream.read(&mut buffer).await.unwrap() > 0);
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n\xff\xfe")
+                .await
+                .unwrap();
+            // Keep the connection open until the client closes it.
+            while stream.read(&mut buffer).await.unwrap() > 0 {}
+        });
+
+        let webhook = Webhook::parse(&format!("http://127.0.0.1:{port}/hook")).unwrap();
+        let status = tokio::time::timeout(Duration::from_secs(5), webhook.post("{}")).await;
+        assert_eq!(status.unwrap().unwrap(), 200);
+        server.await.unwrap();
+    }
+}